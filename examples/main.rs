@@ -17,20 +17,22 @@ fn main() {
             multisampling: 1,
             ..Default::default()
         },
-        Box::new(|_cc| {
+        Box::new(|cc| {
             Box::new(Test(
                 8,
-                RetainedImage::from_color_image(
-                    "",
+                cc.egui_ctx.load_texture(
+                    "uv",
                     load_image_bytes(include_bytes!("uv.png")).unwrap(),
+                    Default::default(),
                 ),
+                SvgHandle::new(ICON).unwrap(),
             ))
         }),
     )
     .unwrap()
 }
 
-struct Test(usize, RetainedImage);
+struct Test(usize, TextureHandle, SvgHandle);
 impl eframe::App for Test {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         #[cfg(feature = "puffin")]
@@ -65,7 +67,31 @@ impl eframe::App for Test {
                     ui.separator();
                     ui.vertical(|ui| {
                         ui.label("uv");
-                        Svg::new(ICON).with_texture(self.1.texture_id(ctx)).show(ui);
+                        Svg::new(ICON).with_texture(self.1.id()).show(ui);
+                        // tiled, scrolling with time
+                        let time = ui.input(|i| i.time) as f32;
+                        Svg::new(ICON)
+                            .with_texture_uv(
+                                self.1.id(),
+                                Rect::from_min_max(Pos2::ZERO, Pos2::new(0.5, 0.5)),
+                                UvTransform {
+                                    offset: Vec2::new(time * 0.25, 0.0),
+                                    scale: Vec2::new(4.0, 2.0),
+                                    rotation: 0.0,
+                                },
+                                TextureWrapMode::Repeat,
+                            )
+                            .with_texture_tint(TextureTint::Original)
+                            .show(ui);
+                        ui.ctx().request_repaint();
+                    });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        ui.label("packed");
+                        static PACKED: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+                        let bytes =
+                            PACKED.get_or_init(|| IconMesh::from_svg(&Svg::new(ICON)).to_bytes());
+                        Svg::from_mesh_bytes(bytes).unwrap().show(ui);
                     });
                 });
                 ui.separator();
@@ -176,6 +202,67 @@ impl eframe::App for Test {
                     ui.label("sized");
                 });
                 ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("lod");
+                    for size in [12.0, 48.0, 128.0] {
+                        Svg::new(ICON)
+                            .with_lod(Lod {
+                                min_feature: 1.0,
+                                ..Default::default()
+                            })
+                            .show_sized(ui, [size * 2.0, size]);
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("pixel snapping");
+                    for snap in [false, true] {
+                        Svg::new(ICON)
+                            .with_pixel_snapping(snap)
+                            .show_sized(ui, [32.0, 16.0]);
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("sprite");
+                    for id in ["square", "circle", "triangle"] {
+                        if let Some(svg) = Svg::from_sprite(include_bytes!("sprite.svg"), id) {
+                            svg.show_sized(ui, [32.0, 32.0]);
+                        }
+                    }
+                });
+                #[cfg(feature = "raster")]
+                {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("raster");
+                        Svg::new(ICON)
+                            .with_render_mode(RenderMode::Raster)
+                            .show_sized(ui, [64.0, 32.0]);
+                        Svg::new(ICON)
+                            .with_render_mode(RenderMode::Raster)
+                            .with_color_from_style()
+                            .with_sense(Sense::hover())
+                            .show_sized(ui, [64.0, 32.0]);
+                    });
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("pattern");
+                    Svg::new(include_bytes!("pattern.svg")).show_sized(ui, [64.0, 32.0]);
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("element");
+                    for id in ["g5655", "circle35"] {
+                        Svg::new(ICON).with_element(id).show_sized(ui, [32.0, 32.0]);
+                    }
+                    Svg::new(ICON)
+                        .with_element_color("circle35", Color32::RED)
+                        .with_element_color_fallback(Color32::GRAY)
+                        .show_sized(ui, [64.0, 32.0]);
+                });
+                ui.separator();
                 let response = Svg::new(ICON)
                     .with_color_from_style()
                     .with_sense(Sense::click_and_drag())
@@ -242,6 +329,30 @@ impl eframe::App for Test {
                     Svg::new(include_bytes!("test_gradient_overflow.svg"))
                         .with_tolerance(0.01)
                         .show_justified(ui);
+                    Svg::new(include_bytes!("test_gradient_overflow.svg"))
+                        .with_gradient_texture(true)
+                        .show_justified(ui);
+                    Svg::new(ICON)
+                        .with_gradient_axis(
+                            &[(0.0, Color32::RED), (1.0, Color32::BLUE)],
+                            GradientAxis::Angle(135.0),
+                            TextureWrapMode::Clamp,
+                        )
+                        .with_gradient_per_element(true)
+                        .show_justified(ui);
+                    Svg::new(ICON)
+                        .with_conic_gradient(
+                            &[
+                                (0.0, Color32::RED),
+                                (1.0 / 3.0, Color32::GREEN),
+                                (2.0 / 3.0, Color32::BLUE),
+                                (1.0, Color32::RED),
+                            ],
+                            Pos2::new(32.0, 16.0),
+                            0.0,
+                            TextureWrapMode::Clamp,
+                        )
+                        .show_justified(ui);
                 });
                 ui.separator();
                 ui.horizontal(|ui| {
@@ -250,7 +361,7 @@ impl eframe::App for Test {
                 });
                 ui.horizontal_wrapped(|ui| {
                     for _ in 0..self.0 {
-                        Svg::new(ICON).show(ui);
+                        self.2.svg().show(ui);
                     }
                 });
             });
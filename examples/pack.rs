@@ -0,0 +1,13 @@
+//! `cargo run --example pack -- <input dir> <output dir> [size]`
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let [_, input, output, rest @ ..] = &args[..] else {
+        eprintln!("usage: pack <input dir> <output dir> [size]");
+        std::process::exit(1);
+    };
+    let size = rest.first().map_or(256.0, |s| s.parse().unwrap());
+
+    let count = egui_svgicon::pack_dir(input, output, size).unwrap();
+    println!("packed {count} icons");
+}
@@ -0,0 +1,508 @@
+use crate::*;
+use egui::epaint::ahash::HashMap;
+use egui::util::cache::CacheTrait;
+use std::sync::Arc;
+
+/// everything a tessellated mesh depends on
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MeshKey {
+    source: u64,
+    /// hash of the element id
+    element: Option<u64>,
+    /// hash of the per element colors
+    element_colors: u64,
+    gradient_interpolation: GradientInterpolation,
+    gradient_texture: bool,
+    /// hash of the gradient override when it is applied while tessellating
+    mesh_gradient: u64,
+    /// the current color, only when gradient stops use it
+    stop_color: Option<Color32>,
+    tolerance: u32,
+    scale_tolerance: bool,
+    min_feature: Option<u32>,
+    scale: [u32; 2],
+    pixels_per_point: u32,
+}
+impl MeshKey {
+    pub fn new(svg: &Svg, scale: Vec2, pixels_per_point: f32) -> Self {
+        // destructure so new fields can't be forgotten here
+        let Svg {
+            source: _,
+            color_override: _,
+            background: _,
+            tolerance,
+            scale_tolerance,
+            fit_mode: _,
+            sense: _,
+            lod,
+            pixel_snapping: _,
+            element,
+            element_colors,
+            current_color,
+            gradient_interpolation,
+            gradient_texture,
+            gradient_per_element: _,
+            texture_tint: _,
+            texture_interact: _,
+            #[cfg(feature = "raster")]
+                render_mode: _,
+        } = svg;
+        Self {
+            source: svg.source_key(),
+            element: element.as_ref().map(|e| stable_hash(&e.id)),
+            element_colors: stable_hash(element_colors),
+            gradient_interpolation: *gradient_interpolation,
+            gradient_texture: *gradient_texture,
+            mesh_gradient: svg.mesh_gradient().map_or(0, stable_hash),
+            stop_color: current_color.filter(|_| {
+                svg.document()
+                    .is_some_and(SvgDocument::has_current_color_stops)
+            }),
+            tolerance: quantize(*tolerance),
+            scale_tolerance: *scale_tolerance,
+            min_feature: lod.map(|lod| quantize(lod.min_feature)),
+            scale: [quantize(scale.x), quantize(scale.y)],
+            pixels_per_point: quantize(pixels_per_point),
+        }
+    }
+}
+
+/// 64 bit FNV-1a with integers hashed little endian, so hashes stay the same between
+/// builds, versions and platforms. everything stored on disk is keyed by it
+pub struct StableHasher(u64);
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+impl std::hash::Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// hash that is the same between runs, builds and platforms
+pub fn stable_hash(value: impl std::hash::Hash) -> u64 {
+    use std::hash::Hasher;
+
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// values kept as long as they are used every frame
+pub struct GenerationCache<K, V> {
+    generation: u32,
+    /// (last used generation, value)
+    entries: HashMap<K, (u32, V)>,
+}
+impl<K, V> Default for GenerationCache<K, V> {
+    fn default() -> Self {
+        Self {
+            generation: 0,
+            entries: HashMap::default(),
+        }
+    }
+}
+impl<K: Eq + std::hash::Hash, V> GenerationCache<K, V> {
+    /// the value of `key`, kept for another frame
+    pub fn get(&mut self, key: &K) -> Option<&mut V> {
+        let (used, value) = self.entries.get_mut(key)?;
+        *used = self.generation;
+        Some(value)
+    }
+    /// the value of `key`, created by `f` if missing and kept for another frame
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> &mut V {
+        let generation = self.generation;
+        let (used, value) = self.entries.entry(key).or_insert_with(|| (generation, f()));
+        *used = generation;
+        value
+    }
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entries.insert(key, (self.generation, value));
+    }
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+        self.entries.retain(|k, (_, v)| f(k, v));
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.values().map(|(_, v)| v)
+    }
+}
+impl<K, V> CacheTrait for GenerationCache<K, V>
+where
+    K: Eq + std::hash::Hash + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    fn update(&mut self) {
+        let generation = self.generation;
+        self.entries.retain(|_, (used, _)| *used == generation);
+        self.generation = self.generation.wrapping_add(1);
+    }
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// tessellated meshes and their uploaded mesh texture
+type MeshCache = GenerationCache<MeshKey, (Arc<IconMesh>, Option<TextureHandle>)>;
+
+/// memory used by the mesh cache
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MeshCacheStats {
+    pub meshes: usize,
+    /// texture mapped shapes, their vertices and indices are counted too
+    pub uv_meshes: usize,
+    /// rasterized icons, only with the `raster` feature
+    pub textures: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    /// approximate heap size of all meshes and textures
+    pub bytes: usize,
+}
+
+pub fn tessellate(
+    ctx: &Context,
+    svg: &Svg,
+    scale: Vec2,
+    pixels_per_point: f32,
+) -> (Arc<IconMesh>, Option<TextureHandle>) {
+    #[cfg(not(feature = "disk_cache"))]
+    let tessellate =
+        |svg, _, scale, pixels_per_point| tessellation::tessellate(svg, scale, pixels_per_point);
+
+    #[cfg(feature = "disk_cache")]
+    use disk_cache::tessellate;
+
+    let key = MeshKey::new(svg, scale, pixels_per_point);
+    let (mesh, texture) = ctx.memory_mut(|mem| {
+        mem.caches
+            .cache::<MeshCache>()
+            .get_or_insert_with(key, || {
+                (tessellate(svg, key, scale, pixels_per_point).into(), None)
+            })
+            .clone()
+    });
+
+    let texture = match (&mesh.texture, texture) {
+        (Some(image), None) => Some(upload(
+            ctx,
+            "svgicon_gradient",
+            image.clone(),
+            |cache: &mut MeshCache, texture| {
+                if let Some((_, cached)) = cache.get(&key) {
+                    *cached = Some(texture);
+                }
+            },
+        )),
+        (_, texture) => texture,
+    };
+    (mesh, texture)
+}
+
+/// upload `image` and `store` the texture in cache `C`
+pub fn upload<C: CacheTrait + Default>(
+    ctx: &Context,
+    name: &str,
+    image: ColorImage,
+    store: impl FnOnce(&mut C, TextureHandle),
+) -> TextureHandle {
+    // uploaded outside of `memory_mut`, loading a texture would deadlock in there
+    let texture = ctx.load_texture(name, image, TextureOptions::LINEAR);
+    ctx.memory_mut(|mem| store(mem.caches.cache::<C>(), texture.clone()));
+    texture
+}
+
+/// texture mapped shapes in `0..1` of the rect they were shown in, by a hash of the
+/// shape and mapping
+type UvCache = GenerationCache<u64, Arc<Mesh>>;
+
+/// [`texture::map_uvs`] for a shape in `rect`. the sliced shape is kept while it is
+/// used, so it is only sliced again when the shape or the texture mapping changes
+pub fn map_uvs(
+    ctx: &Context,
+    shape: &mut Mesh,
+    rect: Rect,
+    transform: UvTransform,
+    uv_rect: Rect,
+    wrap_mode: TextureWrapMode,
+) {
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    let to_unit = |pos: Pos2| ((pos - rect.min) / rect.size()).to_pos2();
+    let mut hasher = egui::epaint::ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+    for v in &shape.vertices {
+        let pos = to_unit(v.pos);
+        (quantize(pos.x), quantize(pos.y), v.color).hash(&mut hasher);
+    }
+    shape.indices.hash(&mut hasher);
+    let UvTransform {
+        offset,
+        scale,
+        rotation,
+    } = transform;
+    let rects = [uv_rect.min, uv_rect.max].map(|p| [p.x, p.y]);
+    [offset.x, offset.y, scale.x, scale.y, rotation]
+        .into_iter()
+        .chain(rects.into_iter().flatten())
+        .for_each(|v| quantize(v).hash(&mut hasher));
+    wrap_mode.hash(&mut hasher);
+    let key = hasher.finish();
+
+    let cached = ctx.memory_mut(|mem| mem.caches.cache::<UvCache>().get(&key).cloned());
+    let unit = cached.unwrap_or_else(|| {
+        let mut unit = Mesh {
+            indices: std::mem::take(&mut shape.indices),
+            vertices: shape
+                .vertices
+                .iter()
+                .map(|v| epaint::Vertex {
+                    pos: to_unit(v.pos),
+                    ..*v
+                })
+                .collect(),
+            texture_id: shape.texture_id,
+        };
+        texture::map_uvs(
+            &mut unit,
+            |pos| transform.transform_pos(pos),
+            uv_rect,
+            wrap_mode,
+        );
+        let unit = Arc::new(unit);
+        ctx.memory_mut(|mem| mem.caches.cache::<UvCache>().insert(key, unit.clone()));
+        unit
+    });
+    shape.indices.clone_from(&unit.indices);
+    shape.vertices = unit
+        .vertices
+        .iter()
+        .map(|v| epaint::Vertex {
+            pos: rect.min + v.pos.to_vec2() * rect.size(),
+            ..*v
+        })
+        .collect();
+}
+
+impl Svg {
+    /// drop all cached icon meshes, texture mapped shapes and rasterized icons
+    pub fn clear_mesh_cache(ctx: &Context) {
+        ctx.memory_mut(|mem| {
+            mem.caches.cache::<MeshCache>().clear();
+            mem.caches.cache::<UvCache>().clear();
+            #[cfg(feature = "raster")]
+            {
+                mem.caches.cache::<raster::RasterCache>().clear();
+                mem.caches.cache::<raster::SegmentCache>().clear();
+            }
+        });
+    }
+    /// drop the cached meshes and rasterized textures of this icon, at every size and
+    /// tolerance. other elements and sprites of the same source are kept. texture
+    /// mapped shapes don't know their icon, they are dropped once they go unused
+    pub fn evict_meshes(&self, ctx: &Context) {
+        let source = self.source_key();
+        let element = self.element.as_ref().map(|e| stable_hash(&e.id));
+        ctx.memory_mut(|mem| {
+            mem.caches
+                .cache::<MeshCache>()
+                .retain(|k, _| k.source != source || k.element != element);
+            #[cfg(feature = "raster")]
+            mem.caches
+                .cache::<raster::RasterCache>()
+                .retain(|k, _| k.source != source || k.element != element);
+        });
+    }
+    /// report how many meshes and textures are cached and how much memory they use
+    pub fn mesh_cache_stats(ctx: &Context) -> MeshCacheStats {
+        ctx.memory_mut(|mem| {
+            let mut stats = MeshCacheStats::default();
+            for (mesh, _) in mem.caches.cache::<MeshCache>().values() {
+                stats.meshes += 1;
+                stats.vertices += mesh.vertices.len();
+                stats.indices += mesh.indices.len();
+                stats.bytes += std::mem::size_of_val(&mesh.vertices[..])
+                    + std::mem::size_of_val(&mesh.indices[..])
+                    + mesh
+                        .texture
+                        .as_ref()
+                        .map_or(0, |t| std::mem::size_of_val(&t.pixels[..]));
+            }
+            for mesh in mem.caches.cache::<UvCache>().values() {
+                stats.uv_meshes += 1;
+                stats.vertices += mesh.vertices.len();
+                stats.indices += mesh.indices.len();
+                stats.bytes += std::mem::size_of_val(&mesh.vertices[..])
+                    + std::mem::size_of_val(&mesh.indices[..]);
+            }
+            #[cfg(feature = "raster")]
+            for texture in mem.caches.cache::<raster::RasterCache>().values() {
+                stats.textures += 1;
+                stats.bytes += texture.size()[0] * texture.size()[1] * 4;
+            }
+            stats
+        })
+    }
+    fn source_key(&self) -> u64 {
+        match self.source {
+            SvgSource::Tree((key, _)) | SvgSource::Mesh((key, _)) => key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPRITE: &[u8] = include_bytes!("../examples/sprite.svg");
+
+    fn key(svg: Svg) -> MeshKey {
+        MeshKey::new(&svg, Vec2::splat(2.0), 1.0)
+    }
+
+    #[test]
+    fn equal_configs_share_a_key() {
+        let svg = || Svg::new(SPRITE).with_element("square").with_tolerance(0.5);
+        assert_eq!(key(svg()), key(svg()));
+        // only the tessellation matters
+        assert_eq!(key(svg()), key(svg().with_color(Color32::RED)));
+    }
+
+    #[test]
+    fn stable_hashes() {
+        use std::hash::Hasher;
+
+        // reference values of FNV-1a
+        let fnv = |bytes: &[u8]| {
+            let mut hasher = StableHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(fnv(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash(0x0102_0304u32), fnv(&[4, 3, 2, 1]));
+        assert_eq!(stable_hash(7usize), stable_hash(7u64));
+    }
+
+    #[test]
+    fn quantized_floats() {
+        let tolerance = |t: f32| key(Svg::new(SPRITE).with_tolerance(t));
+        assert_eq!(tolerance(0.0), tolerance(-0.0));
+        assert_eq!(tolerance(f32::NAN), tolerance(-f32::NAN));
+        assert_eq!(tolerance(f32::NAN), tolerance(f32::from_bits(0x7fc0_1234)));
+        assert_eq!(tolerance(1.0), tolerance(1.0 + 4.0 * f32::EPSILON));
+        assert_eq!(quantize(0.0), quantize(-0.0));
+        assert_ne!(quantize(1.0), quantize(1.01));
+    }
+
+    #[test]
+    fn quantized_gradient_overrides() {
+        let conic = |x: f32| gradient::GradientOverride {
+            colors: vec![gradient::GradientColor {
+                fac: x,
+                color: Color32::RED,
+            }],
+            shape: gradient::OverrideShape::Conic {
+                center: Pos2::new(x, 1.0),
+                start_angle: 0.0,
+            },
+            wrap_mode: TextureWrapMode::Clamp,
+        };
+        let hash = |x: f32| stable_hash(conic(x));
+        assert_eq!(hash(0.0), hash(-0.0));
+        assert_eq!(hash(1.0), hash(1.0 + 4.0 * f32::EPSILON));
+        assert_ne!(hash(1.0), hash(1.01));
+    }
+
+    #[test]
+    fn different_configs_differ() {
+        let svg = || Svg::new(SPRITE);
+        let base = key(svg());
+        assert_ne!(base, key(svg().with_tolerance(0.5)));
+        assert_ne!(base, key(svg().with_element("square")));
+        assert_ne!(
+            key(svg().with_element("square")),
+            key(svg().with_element("circle"))
+        );
+        assert_ne!(base, key(svg().with_element_color("square", Color32::RED)));
+        assert_ne!(
+            key(svg().with_element_color("square", Color32::RED)),
+            key(svg().with_element_color("square", Color32::BLUE))
+        );
+        assert_ne!(base, MeshKey::new(&svg(), Vec2::splat(2.0), 2.0));
+        let conic = |angle| {
+            key(svg().with_conic_gradient(
+                &[(0.0, Color32::RED), (1.0, Color32::BLUE)],
+                Pos2::new(0.5, 0.5),
+                angle,
+                TextureWrapMode::Clamp,
+            ))
+        };
+        // gradient overrides are only tessellated with the `gradient` feature
+        if cfg!(feature = "gradient") {
+            assert_ne!(base, conic(0.0));
+            assert_ne!(conic(0.0), conic(90.0));
+        } else {
+            assert_eq!(base, conic(0.0));
+        }
+    }
+
+    #[test]
+    fn evict_keeps_other_elements() {
+        let ctx = Context::default();
+        let symbols = ["square", "circle"].map(|id| Svg::from_sprite(SPRITE, id).unwrap());
+        let _ = ctx.run(Default::default(), |ctx| {
+            for svg in &symbols {
+                tessellate(ctx, svg, Vec2::splat(1.0), 1.0);
+            }
+        });
+        assert_eq!(Svg::mesh_cache_stats(&ctx).meshes, 2);
+        symbols[0].evict_meshes(&ctx);
+        assert_eq!(Svg::mesh_cache_stats(&ctx).meshes, 1);
+    }
+
+    #[test]
+    fn clear_drops_uv_meshes() {
+        let ctx = Context::default();
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(4.0));
+        let mut shape = Mesh::default();
+        shape.add_colored_rect(rect, Color32::WHITE);
+        map_uvs(
+            &ctx,
+            &mut shape,
+            rect,
+            Default::default(),
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            TextureWrapMode::Clamp,
+        );
+        let stats = Svg::mesh_cache_stats(&ctx);
+        assert_eq!((stats.uv_meshes, stats.vertices), (1, 4));
+        Svg::clear_mesh_cache(&ctx);
+        assert_eq!(Svg::mesh_cache_stats(&ctx).uv_meshes, 0);
+    }
+}
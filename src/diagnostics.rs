@@ -0,0 +1,79 @@
+use egui::epaint::ahash::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DIAGNOSTICS: Mutex<Option<(Sender<Diagnostic>, HashSet<Diagnostic>)>> = Mutex::new(None);
+
+/// something an icon is not drawn as written
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Diagnostic {
+    /// the paint of element `id` is not supported and drawn black
+    UnsupportedPaint { id: String, paint: &'static str },
+    /// the gradient of element `id` is drawn in its average color,
+    /// since it is radial or the `gradient` feature is disabled
+    GradientFallback { id: String },
+    /// a gradient override is drawn in its average color,
+    /// since the `gradient` feature is disabled
+    GradientOverrideFallback,
+    /// no element has the id passed to [`Svg::with_element`](crate::Svg::with_element),
+    /// so nothing is drawn
+    UnknownElement { id: String },
+}
+
+/// send every distinct diagnostic once to `sender`. `None` disables them
+pub fn set_diagnostics(sender: Option<Sender<Diagnostic>>) {
+    let mut diagnostics = DIAGNOSTICS.lock().unwrap();
+    ENABLED.store(sender.is_some(), Ordering::Relaxed);
+    *diagnostics = sender.map(|sender| (sender, Default::default()));
+}
+
+/// send the diagnostic if anyone listens, it is only built then
+pub fn report(diagnostic: impl FnOnce() -> Diagnostic) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut diagnostics = DIAGNOSTICS.lock().unwrap();
+    let Some((sender, reported)) = diagnostics.as_mut() else {
+        return;
+    };
+    let diagnostic = diagnostic();
+    if reported.insert(diagnostic.clone()) && sender.send(diagnostic).is_err() {
+        // nobody is listening anymore
+        ENABLED.store(false, Ordering::Relaxed);
+        *diagnostics = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Svg;
+
+    #[test]
+    fn reports_once() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        set_diagnostics(Some(sender));
+        let svg: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+            <rect id="rect" width="2" height="2"/>
+        </svg>"#;
+        for _ in 0..2 {
+            let _ = Svg::new(svg).with_element("missing");
+        }
+        let _ = Svg::new(svg).with_element("rect");
+        set_diagnostics(None);
+
+        // other tests may report at the same time
+        let unknown = receiver
+            .try_iter()
+            .filter(|d| matches!(d, Diagnostic::UnknownElement { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unknown,
+            [Diagnostic::UnknownElement {
+                id: "missing".into()
+            }]
+        );
+    }
+}
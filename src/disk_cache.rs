@@ -0,0 +1,160 @@
+use crate::*;
+use cache::stable_hash;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+static DISK_CACHE: Mutex<Option<DiskCache>> = Mutex::new(None);
+
+/// persists tessellated meshes in a directory so they survive restarts
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_size: u64,
+    /// total size of the stored meshes, shared between clones. `None` until the first store
+    size: Arc<Mutex<Option<u64>>>,
+}
+impl DiskCache {
+    /// store meshes under `dir`. the directory is created on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: 64 * 1024 * 1024,
+            size: Default::default(),
+        }
+    }
+    /// set the total size in bytes above which the least recently used meshes are evicted
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+    /// delete all stored meshes
+    pub fn clear(&self) -> std::io::Result<()> {
+        *self.size.lock().unwrap() = None;
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension() == Some("mesh".as_ref()) {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{key:016x}.mesh"))
+    }
+    fn load(&self, key: u64) -> Option<IconMesh> {
+        let path = self.path(key);
+        let data = std::fs::read(&path).ok()?;
+        let mesh = (data.len() >= 16)
+            .then(|| data.split_at(8))
+            .and_then(|(head, rest)| {
+                let (body, checksum) = rest.split_at(rest.len() - 8);
+                let valid =
+                    head == key.to_le_bytes() && checksum == stable_hash(body).to_le_bytes();
+                valid.then(|| IconMesh::from_bytes(body).ok()).flatten()
+            });
+        match mesh {
+            Some(_) => {
+                // keep recently used meshes from being evicted
+                let _ = std::fs::File::options()
+                    .append(true)
+                    .open(&path)
+                    .and_then(|f| f.set_modified(std::time::SystemTime::now()));
+            }
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        mesh
+    }
+    fn store(&self, key: u64, mesh: &IconMesh) -> std::io::Result<()> {
+        let body = mesh.to_bytes();
+        let mut data = Vec::with_capacity(body.len() + 16);
+        data.extend_from_slice(&key.to_le_bytes());
+        data.extend_from_slice(&body);
+        data.extend_from_slice(&stable_hash(&body).to_le_bytes());
+
+        std::fs::create_dir_all(&self.dir)?;
+        // write then rename so a crash never leaves a half written mesh behind
+        let tmp = self.path(key).with_extension("tmp");
+        let len = data.len() as u64;
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(tmp, self.path(key))?;
+
+        let mut size = self.size.lock().unwrap();
+        *size = match *size {
+            Some(size) if size + len <= self.max_size => Some(size + len),
+            // the directory is only scanned on the first store and once it is full
+            _ => Some(self.evict()?),
+        };
+        Ok(())
+    }
+    /// evict the least recently used meshes down to 3/4 of the max size if it is exceeded,
+    /// so the next scan is many stores away. returns the size left
+    fn evict(&self) -> std::io::Result<u64> {
+        let mut files = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let meta = entry.metadata().ok()?;
+                (entry.path().extension() == Some("mesh".as_ref()))
+                    .then(|| (meta.modified().ok(), meta.len(), entry.path()))
+            })
+            .collect::<Vec<_>>();
+        let mut size = files.iter().map(|(_, len, _)| len).sum::<u64>();
+        if size <= self.max_size {
+            return Ok(size);
+        }
+        files.sort_unstable_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in files {
+            if size <= self.max_size / 4 * 3 {
+                break;
+            }
+            std::fs::remove_file(path)?;
+            size -= len;
+        }
+        Ok(size)
+    }
+}
+
+/// set the disk cache used by all icons. `None` disables it
+pub fn set_disk_cache(cache: Option<DiskCache>) {
+    *DISK_CACHE.lock().unwrap() = cache;
+}
+
+/// look up the mesh in the disk cache, tessellating and storing it on a miss
+pub fn tessellate(svg: &Svg, key: cache::MeshKey, scale: Vec2, pixels_per_point: f32) -> IconMesh {
+    let cache = DISK_CACHE.lock().unwrap().clone();
+    let Some(cache) = cache.filter(|_| matches!(svg.source, SvgSource::Tree(_))) else {
+        return tessellation::tessellate(svg, scale, pixels_per_point);
+    };
+
+    let key = stable_hash((key, cfg!(feature = "gradient"), env!("CARGO_PKG_VERSION")));
+    cache.load(key).unwrap_or_else(|| {
+        let mesh = tessellation::tessellate(svg, scale, pixels_per_point);
+        let _ = cache.store(key, &mesh);
+        mesh
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_down_to_max_size() {
+        let dir = std::env::temp_dir().join(format!("svgicon_evict_{}", std::process::id()));
+        let mesh = IconMesh::from_svg(&Svg::new(include_bytes!("../examples/test.svg")));
+        let len = mesh.to_bytes().len() as u64 + 16;
+        let cache = DiskCache::new(&dir).with_max_size(len * 8);
+        for key in 0..20 {
+            cache.store(key, &mesh).unwrap();
+        }
+        let stored = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().metadata().unwrap().len())
+            .sum::<u64>();
+        assert!(stored <= len * 8);
+        assert_eq!(*cache.size.lock().unwrap(), Some(stored));
+        assert!(cache.load(19).is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+use crate::*;
+use egui::epaint::ahash::HashSet;
+use std::borrow::Cow;
+use std::rc::Rc;
+
+/// a parsed svg that knows which of its paints are `currentColor`,
+/// so they can be resolved when drawing
+pub struct SvgDocument {
+    pub tree: usvg::Tree,
+    current_color: CurrentColor,
+}
+
+/// paints that are `currentColor`
+#[derive(Default)]
+struct CurrentColor {
+    /// fills and strokes by (path data ptr, is stroke)
+    paints: HashSet<(usize, bool)>,
+    /// gradient stops by (stops ptr, index)
+    stops: HashSet<(usize, usize)>,
+}
+impl std::ops::Deref for SvgDocument {
+    type Target = usvg::Tree;
+    fn deref(&self) -> &usvg::Tree {
+        &self.tree
+    }
+}
+impl SvgDocument {
+    /// parse `data`. documents using `currentColor` are parsed a second time with another
+    /// root `color`, whatever paints changed come from it
+    pub fn parse(data: &[u8]) -> Result<Self, usvg::Error> {
+        let data = decompress(data)?;
+        let data = normalize_current_color(&data);
+        let tree = parse_tree(&data)?;
+        let mut current_color = CurrentColor::default();
+        if let Some(data) = with_root_color(&data, "#ffffff")? {
+            current_color.compare(&tree.root, &parse_tree(data.as_bytes())?.root);
+        }
+        Ok(Self {
+            tree,
+            current_color,
+        })
+    }
+    /// whether the fill or stroke of `path` is `currentColor`
+    pub fn is_current_color(&self, path: &usvg::Path, stroke: bool) -> bool {
+        let key = (Rc::as_ptr(&path.data) as usize, stroke);
+        self.current_color.paints.contains(&key)
+    }
+    /// whether any gradient stop is `currentColor`
+    pub fn has_current_color_stops(&self) -> bool {
+        !self.current_color.stops.is_empty()
+    }
+    /// whether stop `index` of `stops` is `currentColor`
+    pub fn is_current_color_stop(&self, stops: &[usvg::Stop], index: usize) -> bool {
+        let key = (stops.as_ptr() as usize, index);
+        self.current_color.stops.contains(&key)
+    }
+    /// replace the colors of `currentColor` stops with `current_color`, keeping their opacity
+    pub fn resolve_stops(
+        &self,
+        stops: &[usvg::Stop],
+        colors: &mut [gradient::GradientColor],
+        current_color: Color32,
+    ) {
+        if !self.has_current_color_stops() {
+            return;
+        }
+        for (i, (stop, color)) in stops.iter().zip(colors).enumerate() {
+            if self.is_current_color_stop(stops, i) {
+                color.color = current_color.gamma_multiply(stop.opacity.get());
+            }
+        }
+    }
+}
+impl CurrentColor {
+    /// record the paints of `group` that differ from `other`, the same group parsed
+    /// with another root color
+    fn compare(&mut self, group: &usvg::Group, other: &usvg::Group) {
+        for (node, other) in group.children.iter().zip(&other.children) {
+            match (node, other) {
+                (usvg::Node::Group(g), usvg::Node::Group(other)) => self.compare(g, other),
+                (usvg::Node::Path(p), usvg::Node::Path(other)) => {
+                    let key = Rc::as_ptr(&p.data) as usize;
+                    if let (Some(a), Some(b)) = (&p.fill, &other.fill) {
+                        if self.compare_paint(&a.paint, &b.paint) {
+                            self.paints.insert((key, false));
+                        }
+                    }
+                    if let (Some(a), Some(b)) = (&p.stroke, &other.stroke) {
+                        if self.compare_paint(&a.paint, &b.paint) {
+                            self.paints.insert((key, true));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    /// whether the plain colors differ. gradient stops and pattern content are recorded
+    fn compare_paint(&mut self, paint: &usvg::Paint, other: &usvg::Paint) -> bool {
+        let (stops, other) = match (paint, other) {
+            (usvg::Paint::Color(a), usvg::Paint::Color(b)) => return a != b,
+            (usvg::Paint::LinearGradient(a), usvg::Paint::LinearGradient(b)) => {
+                (&a.stops, &b.stops)
+            }
+            (usvg::Paint::RadialGradient(a), usvg::Paint::RadialGradient(b)) => {
+                (&a.stops, &b.stops)
+            }
+            (usvg::Paint::Pattern(a), usvg::Paint::Pattern(b)) => {
+                self.compare(&a.borrow().root, &b.borrow().root);
+                return false;
+            }
+            _ => return false,
+        };
+        for (i, (a, b)) in stops.iter().zip(other).enumerate() {
+            if a.color != b.color {
+                self.stops.insert((stops.as_ptr() as usize, i));
+            }
+        }
+        false
+    }
+}
+
+fn parse_tree(data: &[u8]) -> Result<usvg::Tree, usvg::Error> {
+    use usvg::TreeParsing;
+
+    let mut tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+    // needed to draw single elements and to skip tiny features
+    tree.calculate_abs_transforms();
+    tree.calculate_bounding_boxes();
+    Ok(tree)
+}
+
+/// spell every `currentColor` the way usvg expects, css keywords ignore case
+fn normalize_current_color(data: &[u8]) -> Cow<'_, [u8]> {
+    const KEYWORD: &[u8] = b"currentColor";
+    let found = data
+        .windows(KEYWORD.len())
+        .any(|w| w.eq_ignore_ascii_case(KEYWORD) && w != KEYWORD);
+    if !found {
+        return Cow::Borrowed(data);
+    }
+    let mut data = data.to_vec();
+    let mut i = 0;
+    while i + KEYWORD.len() <= data.len() {
+        if data[i..i + KEYWORD.len()].eq_ignore_ascii_case(KEYWORD) {
+            data[i..i + KEYWORD.len()].copy_from_slice(KEYWORD);
+            i += KEYWORD.len();
+        } else {
+            i += 1;
+        }
+    }
+    Cow::Owned(data)
+}
+
+/// the svg with `color` set on its root element. `None` if nothing uses `currentColor`
+/// or the document sets its own root color
+fn with_root_color(data: &[u8], color: &str) -> Result<Option<String>, usvg::Error> {
+    if !data.windows(12).any(|w| w == b"currentColor") {
+        return Ok(None);
+    }
+    let text = std::str::from_utf8(data).map_err(|_| usvg::Error::NotAnUtf8Str)?;
+    let doc = parse_xml(text)?;
+    let root = doc.root_element();
+    if root.has_attribute("color") {
+        return Ok(None);
+    }
+    // right after the tag name
+    let start = root.range().start;
+    let at = text[start..]
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .map_or(text.len(), |i| start + i);
+    Ok(Some(format!(
+        "{} color=\"{color}\"{}",
+        &text[..at],
+        &text[at..]
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_colors_stay() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 2">
+            <rect width="2" height="2" fill="#01fe02"/>
+            <rect x="2" width="2" height="2" fill="CurrentColor"/>
+        </svg>"##;
+        let mesh = IconMesh::from_svg(&Svg::new(svg));
+        let paints = |paint: VertexPaint| {
+            let vertices = mesh.vertices.iter().filter(move |v| v.paint == paint);
+            vertices.map(|v| v.color).collect::<Vec<_>>()
+        };
+        let explicit = paints(VertexPaint::Color);
+        assert!(!explicit.is_empty());
+        assert!(explicit
+            .iter()
+            .all(|c| *c == Color32::from_rgb(0x01, 0xfe, 0x02)));
+        let current = paints(VertexPaint::CurrentColor);
+        assert!(!current.is_empty());
+        assert!(current.iter().all(|c| *c == Color32::WHITE));
+    }
+
+    #[test]
+    fn resolves_stops() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 2 2">
+            <linearGradient id="g">
+                <stop offset="0" stop-color="currentcolor"/>
+                <stop offset="1" stop-color="currentColor"/>
+            </linearGradient>
+            <rect width="2" height="2" fill="url(#g)"/>
+        </svg>"##;
+        let document = SvgDocument::parse(svg).unwrap();
+        assert!(document.has_current_color_stops());
+        let mesh = IconMesh::from_svg(&Svg::new(svg).with_current_color(Color32::RED));
+        assert!(!mesh.vertices.is_empty());
+        for v in &mesh.vertices {
+            let [r, g, b, a] = v.color.to_array();
+            assert!(r >= 253 && g <= 2 && b <= 2 && a == 255, "{:?}", v.color);
+        }
+    }
+
+    #[test]
+    fn keeps_root_color() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" color="#ff0000">
+            <rect width="2" height="2" fill="currentColor"/>
+        </svg>"##;
+        let document = SvgDocument::parse(svg).unwrap();
+        let usvg::Node::Path(path) = &document.root.children[0] else {
+            panic!("not a path");
+        };
+        assert!(!document.is_current_color(path, false));
+    }
+}
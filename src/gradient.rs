@@ -1,17 +1,127 @@
 use crate::*;
 use lyon::geom::euclid::Vector2D;
 use lyon::geom::Line;
+use lyon::lyon_tessellation::VertexBuffers;
 use lyon::math::Point;
 
+#[derive(Clone, Copy)]
 pub struct GradientColor {
     pub fac: f32,
     pub color: Color32,
 }
 
+/// where the stops of a gradient override go
+#[derive(Clone, Copy)]
+pub enum OverrideShape {
+    Linear(GradientAxis),
+    /// `start_angle` in degrees, clockwise from pointing up
+    Conic {
+        center: Pos2,
+        start_angle: f32,
+    },
+}
+
+/// a gradient override whose axis is placed once its bounding box is known
+pub struct GradientOverride {
+    pub colors: Vec<GradientColor>,
+    pub shape: OverrideShape,
+    pub wrap_mode: TextureWrapMode,
+}
+impl GradientOverride {
+    /// place the axis in `bounding_box`. `display` maps points relative to the shown icon
+    /// to svg coordinates
+    pub fn place(&self, bounding_box: Rect, display: emath::RectTransform) -> Gradient {
+        let axis = match self.shape {
+            OverrideShape::Linear(axis) => axis,
+            OverrideShape::Conic {
+                center,
+                start_angle,
+            } => {
+                return Gradient {
+                    colors: self.colors.clone(),
+                    shape: GradientShape::Conic {
+                        center,
+                        start_angle: start_angle.to_radians(),
+                    },
+                    wrap_mode: self.wrap_mode,
+                }
+            }
+        };
+        let (start, end) = match axis {
+            GradientAxis::ViewBox(start, end) => (start, end),
+            GradientAxis::BoundingBox(start, end) => (
+                bounding_box.lerp_inside(start.to_vec2()),
+                bounding_box.lerp_inside(end.to_vec2()),
+            ),
+            GradientAxis::Display(start, end) => {
+                (display.transform_pos(start), display.transform_pos(end))
+            }
+            GradientAxis::Angle(degrees) => {
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                let direction = Vec2::new(sin, -cos);
+                let size = bounding_box.size();
+                let half = direction * (size.x * sin.abs() + size.y * cos.abs()) / 2.0;
+                (bounding_box.center() - half, bounding_box.center() + half)
+            }
+        };
+        Gradient {
+            colors: self.colors.clone(),
+            shape: GradientShape::Linear { start, end },
+            wrap_mode: self.wrap_mode,
+        }
+    }
+    pub fn average_color(&self, interpolation: GradientInterpolation) -> Color32 {
+        average_color(&self.colors, interpolation)
+    }
+    /// whether the gradient is applied while tessellating instead of when shown
+    pub fn is_tessellated(&self, per_element: bool) -> bool {
+        match self.shape {
+            OverrideShape::Linear(axis) => per_element && axis.is_relative(),
+            // conic gradients need their seam and stops cut into the mesh
+            OverrideShape::Conic { .. } => true,
+        }
+    }
+}
+impl std::hash::Hash for GradientOverride {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for c in &self.colors {
+            (quantize(c.fac), c.color).hash(state);
+        }
+        std::mem::discriminant(&self.shape).hash(state);
+        let values = match self.shape {
+            OverrideShape::Linear(axis) => {
+                std::mem::discriminant(&axis).hash(state);
+                match axis {
+                    GradientAxis::ViewBox(a, b)
+                    | GradientAxis::BoundingBox(a, b)
+                    | GradientAxis::Display(a, b) => [a.x, a.y, b.x, b.y],
+                    GradientAxis::Angle(degrees) => [degrees, 0.0, 0.0, 0.0],
+                }
+            }
+            OverrideShape::Conic {
+                center,
+                start_angle,
+            } => [center.x, center.y, start_angle, 0.0],
+        };
+        values.map(quantize).hash(state);
+        self.wrap_mode.hash(state);
+    }
+}
+
+/// the geometry stops are placed along
+#[derive(Clone, Copy)]
+pub enum GradientShape {
+    /// `0.0` at `start` and `1.0` at `end`
+    Linear { start: Pos2, end: Pos2 },
+    /// `0.0` to `1.0` once around `center`,
+    /// `start_angle` in radians clockwise from pointing up
+    Conic { center: Pos2, start_angle: f32 },
+}
+
+#[derive(Clone)]
 pub struct Gradient {
     pub colors: Vec<GradientColor>,
-    pub start: Pos2,
-    pub end: Pos2,
+    pub shape: GradientShape,
     pub wrap_mode: TextureWrapMode,
 }
 impl Gradient {
@@ -27,16 +137,11 @@ impl Gradient {
             ((p[0].x, p[0].y), (p[1].x, p[1].y))
         };
         Gradient {
-            colors: g
-                .stops
-                .iter()
-                .map(|f| GradientColor {
-                    fac: f.offset.get() as _,
-                    color: to_egui_color(f.color, f.opacity.get()),
-                })
-                .collect(),
-            start: Pos2::new(x1 as _, y1 as _),
-            end: Pos2::new(x2 as _, y2 as _),
+            colors: stop_colors(&g.stops),
+            shape: GradientShape::Linear {
+                start: Pos2::new(x1 as _, y1 as _),
+                end: Pos2::new(x2 as _, y2 as _),
+            },
             wrap_mode: match g.spread_method {
                 usvg::SpreadMethod::Pad => TextureWrapMode::Clamp,
                 usvg::SpreadMethod::Reflect => TextureWrapMode::Mirror,
@@ -44,15 +149,31 @@ impl Gradient {
             },
         }
     }
-    pub fn color_at_pos(&self, pos: Pos2) -> Color32 {
-        let fac = {
-            let line = Line {
-                point: Point::new(self.start.x, self.start.y),
-                vector: Vector2D::new(-(self.end.x - self.start.x), self.end.y - self.start.y).yx(),
-            };
-            line.signed_distance_to_point(&Point::new(pos.x, pos.y)) / line.vector.length()
-        };
-
+    pub fn color_at_pos(&self, pos: Pos2, interpolation: GradientInterpolation) -> Color32 {
+        self.color_at_fac(self.fac_at_pos(pos), interpolation)
+    }
+    /// position along the gradient
+    pub fn fac_at_pos(&self, pos: Pos2) -> f32 {
+        match self.shape {
+            GradientShape::Linear { start, end } => {
+                let line = Line {
+                    point: Point::new(start.x, start.y),
+                    vector: Vector2D::new(-(end.x - start.x), end.y - start.y).yx(),
+                };
+                line.signed_distance_to_point(&Point::new(pos.x, pos.y)) / line.vector.length()
+            }
+            GradientShape::Conic {
+                center,
+                start_angle,
+            } => {
+                let v = pos - center;
+                // clockwise from pointing up, y points down
+                let angle = v.x.atan2(-v.y) - start_angle;
+                angle.rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU
+            }
+        }
+    }
+    pub fn color_at_fac(&self, fac: f32, interpolation: GradientInterpolation) -> Color32 {
         let fac = match self.wrap_mode {
             TextureWrapMode::Clamp => fac,
             TextureWrapMode::Mirror => 1.0 - (fac.abs() % 2.0 - 1.0).abs(),
@@ -70,18 +191,695 @@ impl Gradient {
                 break;
             }
         }
-        macro_rules! mix {
-            ($a:expr,$b:expr,$f:expr) => {{
-                let mut _r = $a;
-                _r = (($a as f64) * (1.0 as f64 - $f as f64) + ($b as f64) * ($f as f64)) as _;
-                _r
-            }};
-        }
-        Color32::from_rgba_premultiplied(
-            mix!(color_a.r(), color_b.r(), local_fac),
-            mix!(color_a.g(), color_b.g(), local_fac),
-            mix!(color_a.b(), color_b.b(), local_fac),
-            mix!(color_a.a(), color_b.a(), local_fac),
+        mix(color_a, color_b, local_fac.clamp(0.0, 1.0), interpolation)
+    }
+}
+
+/// the colors of svg gradient stops
+pub fn stop_colors(stops: &[usvg::Stop]) -> Vec<GradientColor> {
+    stops
+        .iter()
+        .map(|f| GradientColor {
+            fac: f.offset.get() as _,
+            color: to_egui_color(f.color, f.opacity.get()),
+        })
+        .collect()
+}
+
+/// upper bound of iso-lines a shape is sliced along
+const MAX_CUTS: usize = 256;
+/// rays a conic gradient is sliced along at least
+const CONIC_SEGMENTS: usize = 32;
+
+impl Gradient {
+    /// slice the triangles from `start` (vertex, index) on along the iso-lines of the
+    /// gradient stops, so vertex colors can resolve every stop.
+    /// stops are only exact when mixed in srgb, so other spaces get a few extra lines.
+    /// conic gradients are also cut along their seam, with vertices on each side of it.
+    /// new vertices are faded by `opacity`
+    pub fn subdivide(
+        &self,
+        buffer: &mut VertexBuffers<IconVertex, u32>,
+        start: (usize, usize),
+        view_box: Rect,
+        interpolation: GradientInterpolation,
+        opacity: f32,
+    ) {
+        #[cfg(feature = "puffin")]
+        puffin::profile_function!();
+
+        let (first_vertex, first_index) = start;
+        let svg_pos = |v: &IconVertex| view_box.min + v.pos.to_vec2() * view_box.size();
+        let mut facs = buffer.vertices[first_vertex..]
+            .iter()
+            .map(|v| self.fac_at_pos(svg_pos(v)))
+            .collect::<Vec<_>>();
+        let (min, max) = match self.shape {
+            GradientShape::Linear { .. } => facs
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), f| {
+                    (min.min(*f), max.max(*f))
+                }),
+            GradientShape::Conic { .. } => (0.0, 1.0),
+        };
+        let steps = match interpolation.space {
+            ColorSpace::Srgb => 1,
+            _ => 4,
+        };
+        let mut cuts = self.cuts(min, max, steps);
+        if let GradientShape::Conic { .. } = self.shape {
+            // colors don't change linearly along straight edges around a center
+            cuts.extend((1..CONIC_SEGMENTS).map(|i| i as f32 / CONIC_SEGMENTS as f32));
+            cuts.sort_unstable_by(f32::total_cmp);
+            cuts.dedup();
+            cuts.insert(0, 0.0);
+        }
+        if cuts.is_empty() {
+            return;
+        }
+
+        let mut triangles = buffer.indices[first_index..]
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect::<Vec<_>>();
+        buffer.indices.truncate(first_index);
+        let original_vertices = buffer.vertices.len();
+        // conic cuts are rays from the center, triangles around it are split there first
+        // so a cut never runs through the opposite side
+        let center = match self.shape {
+            GradientShape::Conic { center, .. } => {
+                let center_vertex = buffer.vertices.len() as u32;
+                let pos = ((center - view_box.min) / view_box.size()).to_pos2();
+                let mut fanned = Vec::with_capacity(triangles.len());
+                for t in triangles {
+                    let positions = t.map(|i| svg_pos(&buffer.vertices[i as usize]));
+                    let sides = [0, 1, 2].map(|k| {
+                        let (a, b) = (positions[k], positions[(k + 1) % 3]);
+                        (b - a).x * (center - a).y - (b - a).y * (center - a).x
+                    });
+                    let inside = sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0);
+                    if !inside || positions.contains(&center) {
+                        fanned.push(t);
+                        continue;
+                    }
+                    if buffer.vertices.len() as u32 == center_vertex {
+                        buffer.vertices.push(IconVertex {
+                            pos,
+                            ..buffer.vertices[t[0] as usize]
+                        });
+                        facs.push(0.0);
+                    }
+                    // an edge through the center needs no triangle
+                    fanned.extend(
+                        (0..3)
+                            .filter(|&k| sides[k] != 0.0)
+                            .map(|k| [center_vertex, t[k], t[(k + 1) % 3]]),
+                    );
+                }
+                triangles = fanned;
+                (buffer.vertices.len() as u32 > center_vertex).then_some(center_vertex)
+            }
+            GradientShape::Linear { .. } => None,
+        };
+        for cut in cuts {
+            let seam = matches!(self.shape, GradientShape::Conic { .. }) && cut == 0.0;
+            // how far along the edge from `a` to `b` the cut crosses it
+            let edge_t = |vertices: &[IconVertex], facs: &[f32], a: u32, b: u32| match self.shape {
+                GradientShape::Linear { .. } => {
+                    let [fa, fb] = [a, b].map(|i| facs[i as usize - first_vertex]);
+                    (cut - fa) / (fb - fa)
+                }
+                GradientShape::Conic { center, .. } => {
+                    let ray = self.conic_ray(cut);
+                    let [pa, pb] = [a, b].map(|i| svg_pos(&vertices[i as usize]) - center);
+                    let cross = |u: Vec2, v: Vec2| u.x * v.y - u.y * v.x;
+                    cross(ray, pa) / cross(ray, pa - pb)
+                }
+            };
+            let sides = |(vertices, facs): &(&mut Vec<IconVertex>, &mut Vec<f32>), t: [u32; 3]| {
+                let GradientShape::Conic { center, .. } = self.shape else {
+                    return Some(t.map(|i| facs[i as usize - first_vertex] < cut));
+                };
+                let ray = self.conic_ray(cut);
+                let positions = t.map(|i| svg_pos(&vertices[i as usize]));
+                let below =
+                    positions.map(|p| ray.x * (p.y - center.y) - ray.y * (p.x - center.x) < 0.0);
+                // conic cuts are rays, the line through the center also crosses the
+                // triangles on the opposite side
+                let k = split::lone_corner(below)?;
+                let crosses_ray = [(k + 1) % 3, (k + 2) % 3].into_iter().any(|b| {
+                    let t_split = edge_t(vertices, facs, t[k], t[b]);
+                    (positions[k].lerp(positions[b], t_split) - center).dot(ray) > 0.0
+                });
+                crosses_ray.then_some(below)
+            };
+            triangles = split::split_triangles(
+                &mut (&mut buffer.vertices, &mut facs),
+                triangles,
+                sides,
+                seam,
+                |(vertices, facs), a, b, below| {
+                    let t_split = edge_t(vertices, facs, a, b);
+                    let [va, vb] = [a, b].map(|i| vertices[i as usize]);
+                    vertices.push(IconVertex {
+                        pos: va.pos.lerp(vb.pos, t_split),
+                        ..va
+                    });
+                    // each side of the seam gets its own vertex
+                    facs.push(if seam && below { 1.0 } else { cut });
+                    vertices.len() as u32 - 1
+                },
+            );
+        }
+        // the color is undefined at the center, each triangle there gets its own
+        // center vertex colored like the rest of the triangle
+        if let Some(center) = center {
+            let mut first = true;
+            for t in &mut triangles {
+                let Some(k) = t.iter().position(|i| *i == center) else {
+                    continue;
+                };
+                let [a, b] =
+                    [t[(k + 1) % 3], t[(k + 2) % 3]].map(|i| svg_pos(&buffer.vertices[i as usize]));
+                let fac = self.fac_at_pos(a.lerp(b, 0.5));
+                if first {
+                    facs[center as usize - first_vertex] = fac;
+                    first = false;
+                } else {
+                    t[k] = buffer.vertices.len() as u32;
+                    buffer.vertices.push(buffer.vertices[center as usize]);
+                    facs.push(fac);
+                }
+            }
+        }
+        buffer.indices.extend(triangles.into_iter().flatten());
+
+        for (v, fac) in buffer.vertices[original_vertices..]
+            .iter_mut()
+            .zip(&facs[original_vertices - first_vertex..])
+        {
+            v.color = self
+                .color_at_fac(*fac, interpolation)
+                .gamma_multiply(opacity);
+        }
+    }
+    /// color `mesh` drawn in `rect`, sliced along the stops like [`Self::subdivide`].
+    /// `view_box` is the part of the svg shown in `rect`
+    pub fn color_mesh(
+        &self,
+        mesh: &mut Mesh,
+        rect: Rect,
+        view_box: Rect,
+        interpolation: GradientInterpolation,
+    ) {
+        let mut buffer = VertexBuffers {
+            vertices: mesh
+                .vertices
+                .iter()
+                .map(|v| {
+                    let pos = ((v.pos - rect.min) / rect.size()).to_pos2();
+                    IconVertex {
+                        pos,
+                        color: self
+                            .color_at_pos(view_box.lerp_inside(pos.to_vec2()), interpolation),
+                        paint: VertexPaint::Gradient,
+                        uv: v.uv,
+                    }
+                })
+                .collect(),
+            indices: std::mem::take(&mut mesh.indices),
+        };
+        self.subdivide(&mut buffer, (0, 0), view_box, interpolation, 1.0);
+        mesh.indices = buffer.indices;
+        mesh.vertices = buffer
+            .vertices
+            .iter()
+            .map(|v| egui::epaint::Vertex {
+                pos: rect.min + v.pos.to_vec2() * rect.size(),
+                uv: v.uv,
+                color: v.color,
+            })
+            .collect();
+    }
+    /// direction of the iso-line of a conic gradient at `fac`
+    fn conic_ray(&self, fac: f32) -> Vec2 {
+        let GradientShape::Conic { start_angle, .. } = self.shape else {
+            return Vec2::ZERO;
+        };
+        let (sin, cos) = (start_angle + fac * std::f32::consts::TAU).sin_cos();
+        Vec2::new(sin, -cos)
+    }
+    /// gradient positions between `min` and `max` where the color changes its slope
+    fn cuts(&self, min: f32, max: f32, steps: usize) -> Vec<f32> {
+        let mut offsets = Vec::new();
+        for stops in self.colors.windows(2) {
+            for i in 0..steps {
+                offsets
+                    .push(stops[0].fac + (stops[1].fac - stops[0].fac) * i as f32 / steps as f32);
+            }
+        }
+        offsets.extend(self.colors.last().map(|c| c.fac));
+
+        let mut cuts = Vec::new();
+        match self.wrap_mode {
+            TextureWrapMode::Clamp => cuts.extend(offsets),
+            TextureWrapMode::Repeat | TextureWrapMode::Mirror => {
+                let periods = (min.floor() as i64)..(max.ceil() as i64);
+                if periods.end - periods.start > MAX_CUTS as i64 {
+                    return Vec::new();
+                }
+                for period in periods {
+                    let mirrored =
+                        matches!(self.wrap_mode, TextureWrapMode::Mirror) && period % 2 != 0;
+                    cuts.extend(offsets.iter().map(|o| match mirrored {
+                        true => period as f32 + 1.0 - o,
+                        false => period as f32 + o,
+                    }));
+                }
+            }
+        }
+        cuts.retain(|c| *c > min && *c < max);
+        cuts.sort_unstable_by(f32::total_cmp);
+        cuts.dedup();
+        cuts.truncate(MAX_CUTS);
+        cuts
+    }
+}
+
+/// texels per gradient length
+const ATLAS_RESOLUTION: f32 = 256.0;
+const ATLAS_MAX_WIDTH: usize = 1024;
+
+/// gradients baked into the rows of a texture.
+/// row 0 is white so plain colored vertices can share the texture
+#[derive(Default)]
+pub struct GradientAtlas {
+    /// gradient and the range of positions its vertices use
+    rows: Vec<(Gradient, f32, f32)>,
+}
+impl GradientAtlas {
+    /// add a gradient and return its row
+    pub fn push(&mut self, gradient: Gradient) -> usize {
+        self.rows.push((gradient, f32::INFINITY, f32::NEG_INFINITY));
+        self.rows.len()
+    }
+    /// the row a vertex was given, gradients drawn in one color have none
+    fn row(&self, v: &IconVertex) -> Option<usize> {
+        let row = (v.uv.y as usize).checked_sub(1)?;
+        (v.paint == VertexPaint::Gradient && row < self.rows.len()).then_some(row)
+    }
+    /// bake every row over the range used by `vertices`.
+    /// gradient vertices carry their gradient position and row in `uv` until they are
+    /// mapped into the texture here
+    pub fn bake(
+        mut self,
+        vertices: &mut [IconVertex],
+        interpolation: GradientInterpolation,
+    ) -> Option<ColorImage> {
+        if self.rows.is_empty() {
+            return None;
+        }
+        for v in vertices.iter() {
+            if let Some(row) = self.row(v) {
+                let (_, min, max) = &mut self.rows[row];
+                *min = min.min(v.uv.x);
+                *max = max.max(v.uv.x);
+            }
+        }
+        let width = self
+            .rows
+            .iter()
+            .map(|(_, min, max)| ((max - min) * ATLAS_RESOLUTION).ceil() as usize)
+            .max()
+            .unwrap_or_default()
+            .clamp(2, ATLAS_MAX_WIDTH);
+        let height = self.rows.len() + 1;
+
+        let mut image = ColorImage::new([width, height], Color32::WHITE);
+        for (row, (gradient, min, max)) in self.rows.iter_mut().enumerate() {
+            if min > max {
+                (*min, *max) = (0.0, 0.0);
+            }
+            for x in 0..width {
+                let fac = *min + (*max - *min) * x as f32 / (width - 1) as f32;
+                image.pixels[(row + 1) * width + x] = gradient.color_at_fac(fac, interpolation);
+            }
+        }
+        // the first and last texel centers are at the ends of the range
+        let texel = Vec2::new(1.0 / width as f32, 1.0 / height as f32);
+        for v in vertices {
+            v.uv = match self.row(v) {
+                Some(row) => {
+                    let (_, min, max) = self.rows[row];
+                    let fac = if max > min {
+                        (v.uv.x - min) / (max - min)
+                    } else {
+                        0.0
+                    };
+                    Pos2::new(
+                        (0.5 + fac * (width - 1) as f32) * texel.x,
+                        (row as f32 + 1.5) * texel.y,
+                    )
+                }
+                None => (texel * 0.5).to_pos2(),
+            };
+        }
+        Some(image)
+    }
+}
+
+/// the color stops average to between `0.0` and `1.0`, a cheap stand-in for the gradient
+pub fn average_color(colors: &[GradientColor], interpolation: GradientInterpolation) -> Color32 {
+    let (Some(first), Some(last)) = (colors.first(), colors.last()) else {
+        return Color32::TRANSPARENT;
+    };
+    // the first and last stop extend to the ends, every pair in between is weighted by
+    // its length
+    let mut parts = vec![
+        (first.color, first.fac.clamp(0.0, 1.0)),
+        (last.color, 1.0 - last.fac.clamp(0.0, 1.0)),
+    ];
+    parts.extend(colors.windows(2).map(|stops| {
+        let length = stops[1].fac.clamp(0.0, 1.0) - stops[0].fac.clamp(0.0, 1.0);
+        (
+            mix(stops[0].color, stops[1].color, 0.5, interpolation),
+            length.max(0.0),
         )
+    }));
+    let weight = parts.iter().map(|(_, w)| w).sum::<f32>();
+    if weight <= 0.0 {
+        return first.color;
+    }
+    let sum = parts
+        .into_iter()
+        .fold(Rgba::TRANSPARENT, |sum, (color, w)| {
+            sum + Rgba::from(color) * w
+        });
+    Color32::from(sum * (1.0 / weight))
+}
+
+/// mix `a` and `b` in the color space of `interpolation`
+pub fn mix(a: Color32, b: Color32, fac: f32, interpolation: GradientInterpolation) -> Color32 {
+    let [a, b] = [a, b].map(|c| to_space(c, interpolation));
+    from_space(
+        std::array::from_fn(|i| a[i] + (b[i] - a[i]) * fac),
+        interpolation,
+    )
+}
+
+/// `[c0, c1, c2, alpha]` in the color space, premultiplied if asked for
+fn to_space(color: Color32, interpolation: GradientInterpolation) -> [f32; 4] {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let alpha = a as f32 / 255.0;
+    let c = match interpolation.space {
+        ColorSpace::Srgb => [r, g, b].map(|c| c as f32 / 255.0),
+        ColorSpace::LinearRgb => [r, g, b].map(ecolor::linear_f32_from_gamma_u8),
+        ColorSpace::Oklab => linear_to_oklab([r, g, b].map(ecolor::linear_f32_from_gamma_u8)),
+    };
+    let c = match interpolation.premultiplied {
+        true => c.map(|c| c * alpha),
+        false => c,
+    };
+    [c[0], c[1], c[2], alpha]
+}
+
+fn from_space([c0, c1, c2, alpha]: [f32; 4], interpolation: GradientInterpolation) -> Color32 {
+    let c = match interpolation.premultiplied {
+        true if alpha > 0.0 => [c0, c1, c2].map(|c| c / alpha),
+        true => return Color32::TRANSPARENT,
+        false => [c0, c1, c2],
+    };
+    let [r, g, b] = match interpolation.space {
+        ColorSpace::Srgb => c.map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8),
+        ColorSpace::LinearRgb => c.map(ecolor::gamma_u8_from_linear_f32),
+        ColorSpace::Oklab => oklab_to_linear(c).map(ecolor::gamma_u8_from_linear_f32),
+    };
+    Color32::from_rgba_unmultiplied(r, g, b, (alpha * 255.0).round() as u8)
+}
+
+// https://bottosson.github.io/posts/oklab/
+#[allow(clippy::excessive_precision)]
+fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+#[allow(clippy::excessive_precision)]
+fn oklab_to_linear([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960771 * l_ - 0.7034402551 * m_ + 1.6963211812 * s_,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear(facs: &[f32], end: Pos2, wrap_mode: TextureWrapMode) -> Gradient {
+        let colors = [Color32::RED, Color32::GREEN, Color32::BLUE];
+        Gradient {
+            colors: facs
+                .iter()
+                .zip(colors.iter().cycle())
+                .map(|(fac, color)| GradientColor {
+                    fac: *fac,
+                    color: *color,
+                })
+                .collect(),
+            shape: GradientShape::Linear {
+                start: Pos2::ZERO,
+                end,
+            },
+            wrap_mode,
+        }
+    }
+
+    /// two triangles covering the viewbox
+    fn square() -> VertexBuffers<IconVertex, u32> {
+        let mut buffer = VertexBuffers::new();
+        for pos in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] {
+            buffer.vertices.push(IconVertex {
+                pos: pos.into(),
+                color: Color32::WHITE,
+                paint: VertexPaint::Gradient,
+                uv: Pos2::ZERO,
+            });
+        }
+        buffer.indices.extend([0, 1, 2, 0, 2, 3]);
+        buffer
+    }
+
+    fn interpolation(space: ColorSpace, premultiplied: bool) -> GradientInterpolation {
+        GradientInterpolation {
+            space,
+            premultiplied,
+        }
+    }
+
+    #[test]
+    fn mixes_in_color_space() {
+        let srgb = mix(Color32::RED, Color32::BLUE, 0.5, Default::default());
+        assert_eq!(srgb, Color32::from_rgb(128, 0, 128));
+        // halfway in light is brighter once gamma encoded
+        let linear = mix(
+            Color32::RED,
+            Color32::BLUE,
+            0.5,
+            interpolation(ColorSpace::LinearRgb, true),
+        );
+        assert_eq!(linear, Color32::from_rgb(188, 0, 188));
+    }
+
+    #[test]
+    fn premultiplied_mixing_has_no_fringe() {
+        let half = |premultiplied| {
+            let interpolation = interpolation(ColorSpace::Srgb, premultiplied);
+            mix(Color32::WHITE, Color32::TRANSPARENT, 0.5, interpolation).to_srgba_unmultiplied()
+        };
+        let [r, g, b, a] = half(true);
+        assert!(
+            r >= 250 && g >= 250 && b >= 250 && a == 128,
+            "{:?}",
+            [r, g, b, a]
+        );
+        // the black of the transparent stop bleeds in
+        let [r, g, b, a] = half(false);
+        assert!(
+            r <= 130 && g <= 130 && b <= 130 && a == 128,
+            "{:?}",
+            [r, g, b, a]
+        );
+    }
+
+    #[test]
+    fn oklab_round_trips() {
+        let oklab = interpolation(ColorSpace::Oklab, false);
+        let [l, ..] = to_space(Color32::RED, oklab);
+        assert!((l - 0.628).abs() < 1e-3, "{l}");
+        // the published matrices are not exact inverses in f32
+        for color in [Color32::RED, Color32::GREEN, Color32::BLUE, Color32::WHITE] {
+            let back = from_space(to_space(color, oklab), oklab);
+            assert!(
+                (0..4).all(|k| (back[k] as i32 - color[k] as i32).abs() <= 1),
+                "{color:?} {back:?}"
+            );
+        }
+    }
+
+    const VIEW_BOX: Rect = Rect::from_min_max(Pos2::ZERO, Pos2::new(8.0, 8.0));
+
+    #[test]
+    fn linear_cuts_along_stops() {
+        let stops = [0.0, 0.25, 0.5, 1.0];
+        let gradient = linear(&stops, Pos2::new(8.0, 0.0), TextureWrapMode::Clamp);
+        let mut buffer = square();
+        gradient.subdivide(&mut buffer, (0, 0), VIEW_BOX, Default::default(), 1.0);
+        let fac = |i: u32| buffer.vertices[i as usize].pos.x;
+        for stop in [0.25, 0.5] {
+            assert!(buffer.indices.iter().any(|i| (fac(*i) - stop).abs() < 1e-5));
+        }
+        // no triangle spans a stop, so vertex colors reach every stop
+        for t in buffer.indices.chunks_exact(3) {
+            let facs = [fac(t[0]), fac(t[1]), fac(t[2])];
+            let min = facs.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = facs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            assert!(
+                stops.iter().all(|s| *s <= min + 1e-5 || *s >= max - 1e-5),
+                "{facs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn linear_subdivision_is_watertight() {
+        let gradient = linear(
+            &[0.0, 0.3, 0.6, 1.0],
+            Pos2::new(8.0, 8.0),
+            TextureWrapMode::Clamp,
+        );
+        let mut buffer = square();
+        gradient.subdivide(&mut buffer, (0, 0), VIEW_BOX, Default::default(), 1.0);
+        // every edge is shared by two triangles, except those along the outline
+        let mut edges = ahash::HashMap::<(u32, u32), usize>::default();
+        for t in buffer.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let outline = edges
+            .iter()
+            .filter(|(_, count)| **count == 1)
+            .map(|((a, b), _)| {
+                (buffer.vertices[*a as usize].pos - buffer.vertices[*b as usize].pos).length()
+            })
+            .sum::<f32>();
+        assert!((outline - 4.0).abs() < 1e-4, "{outline}");
+        assert!(edges.values().all(|count| *count <= 2));
+    }
+
+    #[test]
+    fn repeating_cuts_are_limited() {
+        // too many periods to cut, the mesh is kept
+        let gradient = linear(&[0.0, 1.0], Pos2::new(0.001, 0.0), TextureWrapMode::Repeat);
+        let mut buffer = square();
+        gradient.subdivide(&mut buffer, (0, 0), VIEW_BOX, Default::default(), 1.0);
+        assert_eq!((buffer.vertices.len(), buffer.indices.len()), (4, 6));
+
+        // 100 periods with 4 cuts each
+        let stops = [0.0, 0.2, 0.4, 0.6, 1.0];
+        let gradient = linear(&stops, Pos2::new(0.08, 0.0), TextureWrapMode::Mirror);
+        let mut buffer = square();
+        gradient.subdivide(&mut buffer, (0, 0), VIEW_BOX, Default::default(), 1.0);
+        let mut xs = buffer.vertices.iter().map(|v| v.pos.x).collect::<Vec<_>>();
+        xs.sort_unstable_by(f32::total_cmp);
+        xs.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+        // the cuts and both sides of the square
+        assert_eq!(xs.len(), MAX_CUTS + 2);
+    }
+
+    #[test]
+    fn conic_seam_is_one_sided() {
+        let gradient = Gradient {
+            colors: vec![
+                GradientColor {
+                    fac: 0.0,
+                    color: Color32::RED,
+                },
+                GradientColor {
+                    fac: 1.0,
+                    color: Color32::BLUE,
+                },
+            ],
+            shape: GradientShape::Conic {
+                center: Pos2::new(4.0, 5.0),
+                start_angle: 0.0,
+            },
+            wrap_mode: TextureWrapMode::Clamp,
+        };
+        let view_box = Rect::from_min_size(Pos2::ZERO, Vec2::splat(8.0));
+        // a triangle around the center
+        let mut buffer = VertexBuffers::new();
+        for pos in [
+            Pos2::new(0.5, 0.0),
+            Pos2::new(1.0, 1.0),
+            Pos2::new(0.0, 1.0),
+        ] {
+            buffer.vertices.push(IconVertex {
+                pos,
+                color: gradient
+                    .color_at_pos(view_box.lerp_inside(pos.to_vec2()), Default::default()),
+                paint: VertexPaint::Gradient,
+                uv: Pos2::ZERO,
+            });
+        }
+        buffer.indices.extend([0, 1, 2]);
+        gradient.subdivide(&mut buffer, (0, 0), view_box, Default::default(), 1.0);
+
+        let mesh = Mesh {
+            indices: buffer.indices,
+            vertices: buffer
+                .vertices
+                .iter()
+                .map(|v| egui::epaint::Vertex {
+                    pos: (v.pos.to_vec2() * 64.0).to_pos2(),
+                    uv: Pos2::ZERO,
+                    color: v.color,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let image = rasterize_mesh(&mesh, None, [64, 64], 1);
+        let close =
+            |a: Color32, b: Color32| (0..4).all(|k| (a[k] as i32 - b[k] as i32).abs() <= 16);
+        // the seam above the center
+        assert!(
+            close(image[(32, 30)], Color32::RED),
+            "{:?}",
+            image[(32, 30)]
+        );
+        assert!(
+            close(image[(31, 30)], Color32::BLUE),
+            "{:?}",
+            image[(31, 30)]
+        );
+        // halfway around below it, on both sides
+        let half = image[(31, 56)];
+        assert!(
+            close(half, image[(32, 56)]),
+            "{half:?} {:?}",
+            image[(32, 56)]
+        );
+        assert!(half.r() > 96 && half.b() > 96, "{half:?}");
     }
 }
@@ -0,0 +1,64 @@
+use crate::*;
+use std::rc::Rc;
+
+/// a parsed icon that is cheap to clone and to turn into [`Svg`]s.
+/// create it once and keep it around instead of calling [`Svg::new`] every frame
+#[derive(Clone)]
+pub struct SvgHandle(pub(crate) SvgSource);
+impl SvgHandle {
+    /// parse a svg icon from buffer
+    #[cfg_attr(feature = "cached", doc = "")]
+    #[cfg_attr(
+        feature = "cached",
+        doc = "`cached`: the tree is dropped with the last handle, meshes are shared by content"
+    )]
+    pub fn new(data: &[u8]) -> Result<Self, usvg::Error> {
+        #[cfg(feature = "puffin")]
+        puffin::profile_function!();
+
+        let tree = Rc::new(load_tree(data)?);
+
+        #[cfg(feature = "cached")]
+        let tree = (content_key::<SvgDocument>(data), tree);
+
+        Ok(Self(SvgSource::Tree(tree)))
+    }
+    /// load a pre-tessellated icon packed by [`IconMesh::to_bytes`] or [`pack_dir`]
+    pub fn from_mesh_bytes(data: &[u8]) -> Result<Self, MeshError> {
+        #[cfg(feature = "puffin")]
+        puffin::profile_function!();
+
+        let mesh = Rc::new(IconMesh::from_bytes(data)?);
+
+        #[cfg(feature = "cached")]
+        let mesh = (content_key::<IconMesh>(data), mesh);
+
+        Ok(Self(SvgSource::Mesh(mesh)))
+    }
+    /// like [`SvgHandle::new`] but shares the tree through the thread local cache
+    pub(crate) fn new_shared(data: &[u8]) -> Result<Self, usvg::Error> {
+        #[cfg(not(feature = "cached"))]
+        return Self::new(data);
+
+        #[cfg(feature = "cached")]
+        Ok(Self(SvgSource::Tree(load_cached(
+            content_key::<SvgDocument>(data),
+            || load_tree(data),
+        )?)))
+    }
+    /// like [`SvgHandle::from_mesh_bytes`] but shares the mesh through the thread local cache
+    pub(crate) fn from_mesh_bytes_shared(data: &[u8]) -> Result<Self, MeshError> {
+        #[cfg(not(feature = "cached"))]
+        return Self::from_mesh_bytes(data);
+
+        #[cfg(feature = "cached")]
+        Ok(Self(SvgSource::Mesh(load_cached(
+            content_key::<IconMesh>(data),
+            || IconMesh::from_bytes(data),
+        )?)))
+    }
+    /// create an icon from this handle
+    pub fn svg(&self) -> Svg {
+        Svg::from_handle(self)
+    }
+}
@@ -0,0 +1,219 @@
+use crate::*;
+use egui::epaint::ahash::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::rc::Rc;
+
+const MAGIC: &[u8; 4] = b"SVGS";
+const VERSION: u16 = 1;
+const DARK_SUFFIX: &str = ".dark";
+
+/// named icons. an icon named `<name>.dark` is used as the dark theme variant of `<name>`
+#[derive(Clone, Default)]
+pub struct IconSet {
+    icons: HashMap<String, SvgHandle>,
+    fallback: Option<String>,
+    config: Option<Rc<dyn Fn(Svg) -> Svg>>,
+}
+impl IconSet {
+    /// an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// load every `.svg` and `.mesh` in `dir`, named by their file stem
+    pub fn from_dir(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut set = Self::new();
+        for (name, is_mesh, data) in read_dir(dir.as_ref())? {
+            set.insert_entry(name, is_mesh, &data)?;
+        }
+        Ok(set)
+    }
+    /// load icons packed by [`pack_icon_set`]
+    pub fn from_packed(data: &[u8]) -> std::io::Result<Self> {
+        let invalid = || Error::new(ErrorKind::InvalidData, "corrupted packed icon set");
+        let mut data = data;
+        let mut take = |n: usize| {
+            if data.len() < n {
+                return Err(invalid());
+            }
+            let (head, tail) = data.split_at(n);
+            data = tail;
+            Ok(head)
+        };
+        if take(4)? != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a packed icon set"));
+        }
+        if take(2)? != VERSION.to_le_bytes() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "unsupported icon set version",
+            ));
+        }
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let mut set = Self::new();
+        for _ in 0..count {
+            let len = u16::from_le_bytes(take(2)?.try_into().unwrap());
+            let name = std::str::from_utf8(take(len as _)?).map_err(|_| invalid())?;
+            let is_mesh = take(1)?[0] != 0;
+            let len = u32::from_le_bytes(take(4)?.try_into().unwrap());
+            set.insert_entry(name.to_owned(), is_mesh, take(len as _)?)?;
+        }
+        Ok(set)
+    }
+    /// add an icon from svg data
+    pub fn insert(&mut self, name: impl Into<String>, data: &[u8]) -> Result<(), usvg::Error> {
+        self.insert_handle(name, SvgHandle::new_shared(data)?);
+        Ok(())
+    }
+    /// add a pre-tessellated icon packed by [`IconMesh::to_bytes`] or [`pack_dir`]
+    pub fn insert_mesh(&mut self, name: impl Into<String>, data: &[u8]) -> Result<(), MeshError> {
+        self.insert_handle(name, SvgHandle::from_mesh_bytes_shared(data)?);
+        Ok(())
+    }
+    /// add an icon that is already loaded
+    pub fn insert_handle(&mut self, name: impl Into<String>, handle: SvgHandle) {
+        self.icons.insert(name.into(), handle);
+    }
+    /// use this icon when a name is missing
+    pub fn with_fallback(mut self, name: impl Into<String>) -> Self {
+        self.fallback = Some(name.into());
+        self
+    }
+    /// configure every icon returned by this set
+    pub fn with_config(mut self, config: impl Fn(Svg) -> Svg + 'static) -> Self {
+        self.config = Some(Rc::new(config));
+        self
+    }
+    /// whether an icon is named `name`, not counting the fallback
+    pub fn contains(&self, name: &str) -> bool {
+        self.icons.contains_key(name)
+    }
+    /// names of all icons, dark variants included
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.icons.keys().map(String::as_str)
+    }
+    /// get the icon or the fallback
+    pub fn get(&self, name: &str) -> Option<Svg> {
+        self.get_themed(name, false)
+    }
+    /// get the icon or the fallback, preferring dark variants in dark mode
+    pub fn get_themed(&self, name: &str, dark_mode: bool) -> Option<Svg> {
+        let find = |name: &str| {
+            dark_mode
+                .then(|| self.icons.get(&format!("{name}{DARK_SUFFIX}")))
+                .flatten()
+                .or_else(|| self.icons.get(name))
+        };
+        let svg = find(name)
+            .or_else(|| find(self.fallback.as_deref()?))?
+            .svg();
+        Some(match &self.config {
+            Some(config) => config(svg),
+            None => svg,
+        })
+    }
+    /// get the icon or the fallback, following the ui's theme
+    pub fn get_for(&self, ui: &Ui, name: &str) -> Option<Svg> {
+        self.get_themed(name, ui.visuals().dark_mode)
+    }
+    fn insert_entry(&mut self, name: String, is_mesh: bool, data: &[u8]) -> std::io::Result<()> {
+        if is_mesh {
+            self.insert_mesh(name, data)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+        } else {
+            self.insert(name, data)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+        }
+    }
+}
+
+/// pack every `.svg` and `.mesh` in `dir` into a single buffer for [`IconSet::from_packed`]
+pub fn pack_icon_set(dir: impl AsRef<Path>) -> std::io::Result<Vec<u8>> {
+    pack_entries(read_dir(dir.as_ref())?)
+}
+
+fn pack_entries(entries: Vec<(String, bool, Vec<u8>)>) -> std::io::Result<Vec<u8>> {
+    let too_large = |what| Error::new(ErrorKind::InvalidInput, format!("{what} too large to pack"));
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    let count = u32::try_from(entries.len()).map_err(|_| too_large("icon set"))?;
+    out.extend_from_slice(&count.to_le_bytes());
+    for (name, is_mesh, data) in entries {
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large("icon name"))?;
+        let data_len = u32::try_from(data.len()).map_err(|_| too_large("icon"))?;
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.push(is_mesh as u8);
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.extend_from_slice(&data);
+    }
+    Ok(out)
+}
+
+/// (name, is mesh, data) of every icon in `dir`
+fn read_dir(dir: &Path) -> std::io::Result<Vec<(String, bool, Vec<u8>)>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_mesh = match path.extension().and_then(|e| e.to_str()) {
+            Some("svg") => false,
+            Some("mesh") => true,
+            _ => continue,
+        };
+        let Some(name) = path.file_stem().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        entries.push((name.to_owned(), is_mesh, std::fs::read(&path)?));
+    }
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_round_trip() {
+        let dir = std::env::temp_dir().join(format!("svgicon_set_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let light = include_bytes!("../examples/test.svg");
+        let dark = include_bytes!("../examples/sprite.svg");
+        std::fs::write(dir.join("icon.svg"), light).unwrap();
+        std::fs::write(dir.join("icon.dark.svg"), dark).unwrap();
+        let mesh = IconMesh::from_svg(&Svg::new(light)).to_bytes();
+        std::fs::write(dir.join("packed.mesh"), mesh).unwrap();
+        std::fs::write(dir.join("notes.txt"), "skipped").unwrap();
+        let packed = pack_icon_set(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let set = IconSet::from_packed(&packed).unwrap().with_fallback("icon");
+        let mut names = set.names().collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, ["icon", "icon.dark", "packed"]);
+        let view_box = |svg: Option<Svg>| svg.unwrap().svg_rect();
+        let light_rect = Svg::new(light).svg_rect();
+        let dark_rect = Svg::new(dark).svg_rect();
+        assert_ne!(light_rect, dark_rect);
+        assert_eq!(view_box(set.get("icon")), light_rect);
+        assert_eq!(view_box(set.get_themed("icon", true)), dark_rect);
+        assert_eq!(view_box(set.get_themed("packed", true)), light_rect);
+        assert_eq!(view_box(set.get("missing")), light_rect);
+        assert!(!set.contains("missing"));
+    }
+
+    #[test]
+    fn pack_rejects_long_names() {
+        let name = "x".repeat(u16::MAX as usize + 1);
+        let error = pack_entries(vec![(name, false, Vec::new())]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn corrupted_pack() {
+        let packed = pack_entries(vec![("a".into(), false, b"<svg/>".to_vec())]).unwrap();
+        assert!(IconSet::from_packed(&packed[..packed.len() - 1]).is_err());
+        assert!(IconSet::from_packed(b"SVGX").is_err());
+    }
+}
@@ -1,18 +1,36 @@
+use document::SvgDocument;
 use egui::*;
 use utils::*;
 
-#[cfg(feature = "gradient")]
+#[cfg(feature = "cached")]
+mod cache;
+mod diagnostics;
+#[cfg(feature = "disk_cache")]
+mod disk_cache;
+mod document;
 mod gradient;
+mod handle;
+mod icon_set;
+mod mesh;
+mod pattern;
+#[cfg(feature = "raster")]
+mod raster;
+mod software;
+mod split;
+mod sprite;
 mod tessellation;
+mod texture;
 mod utils;
 
-/// ???
 #[cfg(feature = "cached")]
-macro_rules! bytes {
-    ($t:expr, $T:ty) => {
-        unsafe { std::mem::transmute::<$T, [u8; std::mem::size_of::<$T>()]>($t) }
-    };
-}
+pub use cache::MeshCacheStats;
+pub use diagnostics::{set_diagnostics, Diagnostic};
+#[cfg(feature = "disk_cache")]
+pub use disk_cache::{set_disk_cache, DiskCache};
+pub use handle::SvgHandle;
+pub use icon_set::{pack_icon_set, IconSet};
+pub use mesh::{pack_dir, IconMesh, IconVertex, MeshError, VertexPaint};
+pub use software::rasterize_mesh;
 
 #[derive(Clone, Copy)]
 pub enum FitMode {
@@ -23,20 +41,162 @@ pub enum FitMode {
     Contain(Margin),
 }
 
-#[derive(Clone, Copy)]
+/// level of detail tiers, from the fewest triangles to the most
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LodTier {
+    Coarse,
+    Medium,
+    Fine,
+}
+
+/// level of detail settings. sizes are in physical pixels
+#[derive(Clone, Copy, Debug)]
+pub struct Lod {
+    /// the coarse tier is used up to this on-screen size
+    pub coarse: f32,
+    /// the medium tier is used up to this on-screen size
+    pub medium: f32,
+    /// paths and strokes smaller than this are skipped. `0.0` keeps everything
+    pub min_feature: f32,
+    /// always use this tier instead of picking one by size
+    pub tier: Option<LodTier>,
+}
+impl Default for Lod {
+    fn default() -> Self {
+        Self {
+            coarse: 24.0,
+            medium: 96.0,
+            min_feature: 0.0,
+            tier: None,
+        }
+    }
+}
+impl Lod {
+    /// the tier used for an icon `pixels` wide (or tall, whichever is larger)
+    pub fn tier_for(&self, pixels: f32) -> LodTier {
+        match self.tier {
+            Some(tier) => tier,
+            None if pixels <= self.coarse => LodTier::Coarse,
+            None if pixels <= self.medium => LodTier::Medium,
+            None => LodTier::Fine,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum TextureWrapMode {
     Clamp,
     Repeat,
     Mirror,
 }
 
+/// how a texture override is laid over the icon. the texture coordinates of a point `p`,
+/// `0.0` to `1.0` across the icon, are `rotate(-rotation) * ((p - 0.5) * scale) + 0.5 - offset`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct UvTransform {
+    /// moves the texture, in texture sizes. animate it to scroll the texture
+    pub offset: Vec2,
+    /// how often the texture fits across the icon
+    pub scale: Vec2,
+    /// clockwise rotation around the icon's center, in radians
+    pub rotation: f32,
+}
+impl Default for UvTransform {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            scale: Vec2::splat(1.0),
+            rotation: 0.0,
+        }
+    }
+}
+impl UvTransform {
+    fn transform_pos(&self, p: Pos2) -> Pos2 {
+        let centered = (p - Pos2::new(0.5, 0.5)) * self.scale;
+        Pos2::new(0.5, 0.5) + emath::Rot2::from_angle(-self.rotation) * centered - self.offset
+    }
+}
+
+/// how an icon is drawn
+#[cfg(feature = "raster")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderMode {
+    /// tessellated into triangles
+    Vector,
+    /// rasterized into a texture at the shown size. texture and gradient overrides
+    /// are still drawn as vectors
+    Raster,
+    /// rasterized if the icon has many path segments
+    Auto,
+}
+
+/// what a texture override is multiplied with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextureTint {
+    /// a fixed color, white leaves the texture as is
+    Color(Color32),
+    /// the svg's own colors. gradients baked by `with_gradient_texture` count as white
+    Original,
+}
+
+/// the axis of a gradient override, from its `0.0` stop to its `1.0` stop
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GradientAxis {
+    /// points in svg viewbox coordinates
+    ViewBox(Pos2, Pos2),
+    /// points as fractions of the bounding box, like svg's `objectBoundingBox`
+    BoundingBox(Pos2, Pos2),
+    /// points relative to the top left of the shown icon
+    Display(Pos2, Pos2),
+    /// like css `linear-gradient(<angle>deg, ..)`: clockwise from pointing up,
+    /// sized so the stops reach the corners of the bounding box
+    Angle(f32),
+}
+impl GradientAxis {
+    /// whether the axis is placed relative to a bounding box
+    fn is_relative(&self) -> bool {
+        matches!(self, GradientAxis::BoundingBox(..) | GradientAxis::Angle(_))
+    }
+}
+
+/// color space gradient stops are mixed in
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ColorSpace {
+    /// gamma encoded, svg's `color-interpolation: sRGB`
+    Srgb,
+    /// svg's `color-interpolation: linearRGB`
+    LinearRgb,
+    /// perceptually uniform
+    Oklab,
+}
+
+/// how colors between gradient stops are computed
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GradientInterpolation {
+    pub space: ColorSpace,
+    /// mix colors premultiplied by alpha, so transparent stops don't darken their neighbours
+    pub premultiplied: bool,
+}
+impl Default for GradientInterpolation {
+    fn default() -> Self {
+        Self {
+            space: ColorSpace::Srgb,
+            premultiplied: true,
+        }
+    }
+}
+
 enum ColorOverride {
     None,
     FromStyle,
     Color(Color32),
-    Texture(TextureId),
-    #[cfg(feature = "gradient")]
-    Gradient(gradient::Gradient),
+    Texture {
+        texture: TextureId,
+        uv_rect: Rect,
+        transform: UvTransform,
+        wrap_mode: TextureWrapMode,
+    },
+    Gradient(gradient::GradientOverride),
 }
 
 enum Background {
@@ -50,51 +210,81 @@ enum Background {
 }
 
 #[cfg(not(feature = "cached"))]
-type SvgTree = usvg::Tree;
+type SvgTree = std::rc::Rc<SvgDocument>;
 #[cfg(feature = "cached")]
-type SvgTree = (u64, std::rc::Rc<usvg::Tree>);
+type SvgTree = (u64, std::rc::Rc<SvgDocument>);
+
+#[cfg(not(feature = "cached"))]
+type SvgMesh = std::rc::Rc<IconMesh>;
+#[cfg(feature = "cached")]
+type SvgMesh = (u64, std::rc::Rc<IconMesh>);
+
+#[derive(Clone)]
+enum SvgSource {
+    Tree(SvgTree),
+    Mesh(SvgMesh),
+}
+
+/// a single node of the tree that is drawn instead of the whole document
+struct SvgElement {
+    id: String,
+    /// replaces the document viewbox
+    rect: Rect,
+}
+
+/// colors applied during tessellation to elements by id
+#[derive(Default, Hash)]
+struct ElementColors {
+    colors: Vec<(String, Color32)>,
+    /// used for everything not inside an element with a color
+    fallback: Option<Color32>,
+}
+impl ElementColors {
+    fn get(&self, id: &str) -> Option<Color32> {
+        (!id.is_empty())
+            .then(|| self.colors.iter().rev().find(|(i, _)| i == id))
+            .flatten()
+            .map(|(_, color)| *color)
+    }
+    /// the color of the element with `id` below `group`, inherited from the innermost
+    /// colored ancestor. `None` if the element is not found
+    fn inherited(
+        &self,
+        group: &usvg::Group,
+        id: &str,
+        parent: Option<Color32>,
+    ) -> Option<Option<Color32>> {
+        group.children.iter().find_map(|node| {
+            let color = self.get(node.id()).or(parent);
+            match node {
+                _ if node.id() == id => Some(color),
+                usvg::Node::Group(g) => self.inherited(g, id, color),
+                _ => None,
+            }
+        })
+    }
+}
 
 pub struct Svg {
-    tree: SvgTree,
+    source: SvgSource,
     color_override: ColorOverride,
     background: Background,
     tolerance: f32,
     scale_tolerance: bool,
     fit_mode: FitMode,
     sense: Sense,
-}
-#[cfg(feature = "cached")]
-impl std::hash::Hash for Svg {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        let Self {
-            tree: (key, _),
-            color_override: _,
-            background: _,
-            tolerance,
-            scale_tolerance,
-            fit_mode,
-            sense: _,
-        } = self;
-        key.hash(state);
-        bytes!(*tolerance, f32).hash(state);
-        scale_tolerance.hash(state);
-        match fit_mode {
-            FitMode::None => 0usize.hash(state),
-            FitMode::Size(s) => {
-                1usize.hash(state);
-                bytes!(*s, Vec2).hash(state);
-            }
-            FitMode::Factor(f) => {
-                2usize.hash(state);
-                bytes!(*f, f32).hash(state);
-            }
-            FitMode::Cover => 3usize.hash(state),
-            FitMode::Contain(margin) => {
-                4usize.hash(state);
-                bytes!(*margin, Margin).hash(state);
-            }
-        }
-    }
+    lod: Option<Lod>,
+    pixel_snapping: bool,
+    element: Option<SvgElement>,
+    element_colors: ElementColors,
+    current_color: Option<Color32>,
+    gradient_interpolation: GradientInterpolation,
+    gradient_texture: bool,
+    gradient_per_element: bool,
+    texture_tint: TextureTint,
+    texture_interact: bool,
+    #[cfg(feature = "raster")]
+    render_mode: RenderMode,
 }
 impl Svg {
     /// load a svg icon from buffer
@@ -103,89 +293,236 @@ impl Svg {
     #[cfg_attr(feature = "static_cached", doc = "")]
     #[cfg_attr(
         feature = "static_cached",
-        doc = "`static_cached`: using ptr as cache key so `data` must be `'static`"
+        doc = "`static_cached`: `data` is only hashed once per ptr so it must be `'static`"
     )]
     pub fn new(
         #[cfg(not(feature = "static_cached"))] data: &[u8],
         #[cfg(feature = "static_cached")] data: &'static [u8],
     ) -> Self {
-        use usvg::TreeParsing;
+        #[cfg(feature = "puffin")]
+        puffin::profile_function!();
+
+        #[cfg(not(feature = "cached"))]
+        let tree = std::rc::Rc::new(load_tree(data).unwrap());
 
+        #[cfg(feature = "cached")]
+        let tree = load_cached(data_key::<SvgDocument>(data), || load_tree(data)).unwrap();
+
+        Self::from_source(SvgSource::Tree(tree))
+    }
+    /// load a pre-tessellated icon packed by [`IconMesh::to_bytes`] or [`pack_dir`]
+    #[cfg_attr(feature = "cached", doc = "")]
+    #[cfg_attr(feature = "cached", doc = "`cached`: cached mesh will never drop")]
+    #[cfg_attr(feature = "static_cached", doc = "")]
+    #[cfg_attr(
+        feature = "static_cached",
+        doc = "`static_cached`: `data` is only hashed once per ptr so it must be `'static`"
+    )]
+    pub fn from_mesh_bytes(
+        #[cfg(not(feature = "static_cached"))] data: &[u8],
+        #[cfg(feature = "static_cached")] data: &'static [u8],
+    ) -> Result<Self, MeshError> {
         #[cfg(feature = "puffin")]
         puffin::profile_function!();
 
         #[cfg(not(feature = "cached"))]
-        let tree = usvg::Tree::from_data(data, &usvg::Options::default()).unwrap();
+        let mesh = std::rc::Rc::new(IconMesh::from_bytes(data)?);
 
         #[cfg(feature = "cached")]
-        let tree = {
-            use egui::epaint::ahash::*;
-            use std::cell::RefCell;
-            use std::hash::BuildHasher;
-            use std::hash::Hash;
-            use std::hash::Hasher;
-            use std::rc::Rc;
-
-            thread_local! {
-                static CACHE: RefCell<HashMap<u64, Rc<usvg::Tree>>> = Default::default();
-            }
-            CACHE.with(|cache| {
-                let key = {
-                    let mut hasher = RandomState::with_seed(0).build_hasher();
-
-                    #[cfg(not(feature = "static_cached"))]
-                    data.hash(&mut hasher);
-
-                    #[cfg(feature = "static_cached")]
-                    data.as_ptr().hash(&mut hasher);
-
-                    hasher.finish()
-                };
-
-                (
-                    key,
-                    cache
-                        .borrow_mut()
-                        .entry(key)
-                        .or_insert_with(|| {
-                            Rc::new(usvg::Tree::from_data(data, &usvg::Options::default()).unwrap())
-                        })
-                        .clone(),
-                )
-            })
-        };
+        let mesh = load_cached(data_key::<IconMesh>(data), || IconMesh::from_bytes(data))?;
 
+        Ok(Self::from_source(SvgSource::Mesh(mesh)))
+    }
+    /// load the `<symbol>` with `id` from a sprite sheet, framed by the symbol's own viewbox.
+    /// `None` if the sheet does not parse or there is no such symbol
+    #[cfg_attr(feature = "cached", doc = "")]
+    #[cfg_attr(
+        feature = "cached",
+        doc = "`cached`: the sheet is parsed once and shared by all its icons, it will never drop"
+    )]
+    #[cfg_attr(feature = "static_cached", doc = "")]
+    #[cfg_attr(
+        feature = "static_cached",
+        doc = "`static_cached`: `data` is only hashed once per ptr so it must be `'static`"
+    )]
+    pub fn from_sprite(
+        #[cfg(not(feature = "static_cached"))] data: &[u8],
+        #[cfg(feature = "static_cached")] data: &'static [u8],
+        id: &str,
+    ) -> Option<Self> {
+        #[cfg(feature = "puffin")]
+        puffin::profile_function!();
+
+        #[cfg(not(feature = "cached"))]
+        let sheet = sprite::SpriteSheet::parse(data).ok()?;
+        #[cfg(not(feature = "cached"))]
+        let tree = sheet.tree.clone();
+
+        #[cfg(feature = "cached")]
+        let (key, sheet) = load_cached(data_key::<sprite::SpriteSheet>(data), || {
+            sprite::SpriteSheet::parse(data)
+        })
+        .ok()?;
+        #[cfg(feature = "cached")]
+        let tree = (key, sheet.tree.clone());
+
+        let (id, rect) = sheet.symbols.get(id)?.clone();
+        let mut svg = Self::from_source(SvgSource::Tree(tree));
+        svg.element = Some(SvgElement { id, rect });
+        Some(svg)
+    }
+    /// create an icon from a handle without hashing or parsing again
+    pub fn from_handle(handle: &SvgHandle) -> Self {
+        Self::from_source(handle.0.clone())
+    }
+    /// wrap a tree without going through the cache
+    fn from_tree(tree: SvgDocument) -> Self {
+        #[cfg(not(feature = "cached"))]
+        let tree = std::rc::Rc::new(tree);
+
+        #[cfg(feature = "cached")]
+        let tree = (0, std::rc::Rc::new(tree));
+
+        Self::from_source(SvgSource::Tree(tree))
+    }
+    fn from_source(source: SvgSource) -> Self {
         Svg {
-            tree,
+            source,
             color_override: ColorOverride::None,
             background: Background::None,
             tolerance: 1.0,
             scale_tolerance: true,
             fit_mode: FitMode::Contain(Default::default()),
             sense: Sense::hover(),
+            lod: None,
+            pixel_snapping: false,
+            element: None,
+            element_colors: Default::default(),
+            current_color: None,
+            gradient_interpolation: Default::default(),
+            gradient_texture: false,
+            gradient_per_element: false,
+            texture_tint: TextureTint::Color(Color32::WHITE),
+            texture_interact: false,
+            #[cfg(feature = "raster")]
+            render_mode: RenderMode::Vector,
         }
     }
-    /// set the tessellation tolerance
+    /// set the tessellation tolerance, in physical pixels when scaled
     pub fn with_tolerance(mut self, tolerance: f32) -> Self {
         self.tolerance = tolerance;
         self
     }
-    /// set whether the tessellation tolerance is affected by the scale
+    /// set whether the tessellation tolerance is affected by the scale and `pixels_per_point`
     pub fn with_scale_tolerance(mut self, scale_tolerance: bool) -> Self {
         self.scale_tolerance = scale_tolerance;
         self
     }
+    /// switch between coarse / medium / fine meshes by on-screen size
+    pub fn with_lod(mut self, lod: Lod) -> Self {
+        self.lod = Some(lod);
+        self
+    }
+    /// snap the icon and its axis aligned edges to the physical pixel grid.
+    /// makes small icons look sharper
+    pub fn with_pixel_snapping(mut self, pixel_snapping: bool) -> Self {
+        self.pixel_snapping = pixel_snapping;
+        self
+    }
+    /// only draw the element with `id` and its children, framed by its bounding box.
+    /// does nothing for pre-tessellated icons. an unknown `id` draws nothing
+    /// and is reported as [`Diagnostic::UnknownElement`]
+    pub fn with_element(mut self, id: &str) -> Self {
+        let tree = match &self.source {
+            #[cfg(not(feature = "cached"))]
+            SvgSource::Tree(tree) => tree,
+            #[cfg(feature = "cached")]
+            SvgSource::Tree((_, tree)) => tree,
+            SvgSource::Mesh(_) => return self,
+        };
+        let node = tree.node_by_id(id);
+        if node.is_none() {
+            diagnostics::report(|| Diagnostic::UnknownElement { id: id.to_owned() });
+        }
+        let rect = node
+            .and_then(|node| {
+                node.abs_stroke_bounding_box()
+                    .or_else(|| node.abs_bounding_box()?.to_non_zero_rect())
+            })
+            .map_or(to_egui_rect(tree.view_box.rect), to_egui_rect);
+        self.element = Some(SvgElement {
+            id: id.to_owned(),
+            rect,
+        });
+        self
+    }
+    /// color the element with `id` and its children, keeping their opacity.
+    /// unlike [`Svg::with_color`] other elements keep their own colors.
+    /// elements are only matched by id, classes are not kept by the svg parser
+    pub fn with_element_color(mut self, id: &str, color: Color32) -> Self {
+        self.element_colors.colors.push((id.to_owned(), color));
+        self
+    }
+    /// color everything not colored by [`Svg::with_element_color`]
+    pub fn with_element_color_fallback(mut self, color: Color32) -> Self {
+        self.element_colors.fallback = Some(color);
+        self
+    }
+    /// set the color of `currentColor` paints. defaults to fg_stroke.
+    /// gradient stops using `currentColor` are baked into meshes, black when not set
+    pub fn with_current_color(mut self, color: Color32) -> Self {
+        self.current_color = Some(color);
+        self
+    }
     /// override all elements' color
     pub fn with_color(mut self, color: Color32) -> Self {
         self.color_override = ColorOverride::Color(color);
         self
     }
     /// override all elements' color with given texture
-    pub fn with_texture(mut self, texture: TextureId) -> Self {
-        self.color_override = ColorOverride::Texture(texture);
+    pub fn with_texture(self, texture: TextureId) -> Self {
+        self.with_texture_uv(
+            texture,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            UvTransform::default(),
+            TextureWrapMode::Clamp,
+        )
+    }
+    /// set whether the icon is tessellated or rasterized, tessellated by default
+    #[cfg(feature = "raster")]
+    pub fn with_render_mode(mut self, render_mode: RenderMode) -> Self {
+        self.render_mode = render_mode;
+        self
+    }
+    /// set what the texture override is multiplied with, white by default
+    pub fn with_texture_tint(mut self, tint: TextureTint) -> Self {
+        self.texture_tint = tint;
+        self
+    }
+    /// also multiply the texture override with fg_stroke of the widget's interaction state
+    pub fn with_texture_interact(mut self, interact: bool) -> Self {
+        self.texture_interact = interact;
+        self
+    }
+    /// override all elements' color with the part `uv_rect` of given texture, laid over
+    /// the icon with `transform` and repeated past its edges with `wrap_mode`
+    pub fn with_texture_uv(
+        mut self,
+        texture: TextureId,
+        uv_rect: Rect,
+        transform: UvTransform,
+        wrap_mode: TextureWrapMode,
+    ) -> Self {
+        self.color_override = ColorOverride::Texture {
+            texture,
+            uv_rect,
+            transform,
+            wrap_mode,
+        };
         self
     }
-    /// override all elements' color with given gradient
+    /// override all elements' color with given gradient, `start` and `end` are in the viewbox.
+    /// without the `gradient` feature gradients are drawn in the average color of their stops
     pub fn with_gradient(
         self,
         colors: &[(f32, Color32)],
@@ -193,26 +530,67 @@ impl Svg {
         end: Pos2,
         wrap_mode: TextureWrapMode,
     ) -> Self {
-        #[cfg(not(feature = "gradient"))]
-        {
-            let _ = (colors, start, end, wrap_mode);
-            self
-        }
-        #[cfg(feature = "gradient")]
-        {
-            let mut svg = self;
-            svg.color_override = ColorOverride::Gradient(gradient::Gradient {
-                colors: colors
-                    .iter()
-                    .copied()
-                    .map(|(fac, color)| gradient::GradientColor { fac, color })
-                    .collect(),
-                start,
-                end,
-                wrap_mode,
-            });
-            svg
-        }
+        self.with_gradient_axis(colors, GradientAxis::ViewBox(start, end), wrap_mode)
+    }
+    /// override all elements' color with given gradient along `axis`
+    pub fn with_gradient_axis(
+        mut self,
+        colors: &[(f32, Color32)],
+        axis: GradientAxis,
+        wrap_mode: TextureWrapMode,
+    ) -> Self {
+        self.color_override = ColorOverride::Gradient(gradient::GradientOverride {
+            colors: colors
+                .iter()
+                .copied()
+                .map(|(fac, color)| gradient::GradientColor { fac, color })
+                .collect(),
+            shape: gradient::OverrideShape::Linear(axis),
+            wrap_mode,
+        });
+        self
+    }
+    /// override all elements' color with a gradient sweeping once around `center`,
+    /// in the viewbox. like css `conic-gradient(from <angle>deg, ..)`, `start_angle`
+    /// is in degrees clockwise from pointing up
+    pub fn with_conic_gradient(
+        mut self,
+        colors: &[(f32, Color32)],
+        center: Pos2,
+        start_angle: f32,
+        wrap_mode: TextureWrapMode,
+    ) -> Self {
+        self.color_override = ColorOverride::Gradient(gradient::GradientOverride {
+            colors: colors
+                .iter()
+                .copied()
+                .map(|(fac, color)| gradient::GradientColor { fac, color })
+                .collect(),
+            shape: gradient::OverrideShape::Conic {
+                center,
+                start_angle,
+            },
+            wrap_mode,
+        });
+        self
+    }
+    /// place bounding box relative gradient overrides in every element's bounding box
+    /// instead of the whole icon's
+    pub fn with_gradient_per_element(mut self, per_element: bool) -> Self {
+        self.gradient_per_element = per_element;
+        self
+    }
+    /// set how svg gradients and the gradient override mix their stops
+    pub fn with_gradient_interpolation(mut self, interpolation: GradientInterpolation) -> Self {
+        self.gradient_interpolation = interpolation;
+        self
+    }
+    /// bake svg gradients into a texture instead of vertex colors,
+    /// so they are exact no matter how few vertices a shape has.
+    /// without the `cached` feature the texture is uploaded again every frame
+    pub fn with_gradient_texture(mut self, gradient_texture: bool) -> Self {
+        self.gradient_texture = gradient_texture;
+        self
     }
     /// override all elements' color with fg_stroke
     pub fn with_color_from_style(mut self) -> Self {
@@ -260,7 +638,7 @@ impl Svg {
         self.show_sized(ui, size)
     }
     /// show the icon at the given size
-    pub fn show_sized(self, ui: &mut Ui, size: impl Into<Vec2>) -> Response {
+    pub fn show_sized(mut self, ui: &mut Ui, size: impl Into<Vec2>) -> Response {
         #[cfg(feature = "puffin")]
         puffin::profile_function!();
 
@@ -304,7 +682,13 @@ impl Svg {
                 )
             }
         };
-        let rect = Align2::CENTER_CENTER.align_size_within_rect(size, inner_frame_rect);
+        let mut rect = Align2::CENTER_CENTER.align_size_within_rect(size, inner_frame_rect);
+        if self.pixel_snapping {
+            rect = Rect::from_min_size(
+                ui.painter().round_pos_to_pixels(rect.min),
+                ui.painter().round_vec_to_pixels(rect.size()),
+            );
+        }
         let response = ui.interact(frame_rect, id, self.sense);
 
         #[cfg(feature = "culled")]
@@ -312,76 +696,35 @@ impl Svg {
             return response;
         }
 
-        #[cfg(not(feature = "cached"))]
-        let mut shape = tessellation::tessellate(&self, rect, size / self.svg_rect().size());
-
-        #[cfg(feature = "cached")]
-        let mut shape = {
-            use egui::util::cache::*;
-            use std::hash::*;
-
-            #[derive(Clone, Copy)]
-            struct TessellateCacheKey<'l>(&'l Svg, Vec2);
-            impl Hash for TessellateCacheKey<'_> {
-                fn hash<H: Hasher>(&self, state: &mut H) {
-                    let TessellateCacheKey(svg, size) = self;
-                    svg.hash(state);
-                    bytes!(*size, Vec2).hash(state);
-                }
-            }
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let current_color = self
+            .current_color
+            .unwrap_or_else(|| ui.style().interact(&response).fg_stroke.color);
+        // gradient stops using `currentColor` are resolved while tessellating
+        self.current_color = Some(current_color);
 
-            #[derive(Default)]
-            struct Tessellator;
-            impl ComputerMut<TessellateCacheKey<'_>, Mesh> for Tessellator {
-                fn compute(&mut self, TessellateCacheKey(svg, size): TessellateCacheKey) -> Mesh {
-                    tessellation::tessellate(
-                        svg,
-                        Rect::from_min_size(Pos2::ZERO, size),
-                        size / svg.svg_rect().size(),
-                    )
-                }
-            }
+        #[cfg(feature = "raster")]
+        let raster = self.rasterized(ui.ctx(), rect.size() * pixels_per_point, current_color);
+        #[cfg(not(feature = "raster"))]
+        let raster: Option<TextureHandle> = None;
 
-            let mut mesh = ui.memory_mut(|mem| {
-                mem.caches
-                    .cache::<FrameCache<_, Tessellator>>()
-                    .get(TessellateCacheKey(&self, size))
-            });
-            mesh.translate(rect.min.to_vec2());
-            mesh
-        };
-        macro_rules! svg_pos {
-            ($v:expr) => {
-                (($v.pos - rect.min) * (self.svg_rect().size() / rect.size())
-                    + self.svg_rect().min.to_vec2())
-                .to_pos2()
-            };
-        }
-        match &self.color_override {
-            ColorOverride::None => {}
-            ColorOverride::FromStyle => {
-                shape
-                    .vertices
-                    .iter_mut()
-                    .for_each(|v| v.color = ui.style().interact(&response).fg_stroke.color);
-            }
-            ColorOverride::Color(c) => shape.vertices.iter_mut().for_each(|v| v.color = *c),
-            ColorOverride::Texture(t) => {
-                shape.texture_id = *t;
-                shape.vertices.iter_mut().for_each(|v| {
-                    v.color = Color32::WHITE;
-                    v.uv = (svg_pos!(v).to_vec2() / self.svg_rect().size()).to_pos2();
-                });
-            }
-            #[cfg(feature = "gradient")]
-            ColorOverride::Gradient(g) => {
+        let mut shape = match raster {
+            Some(texture) => {
+                let mut shape = Mesh::with_texture(texture.id());
+                let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+                shape.add_rect_with_uv(rect, uv, Color32::WHITE);
                 shape
-                    .vertices
-                    .iter_mut()
-                    .for_each(|v| v.color = g.color_at_pos(svg_pos!(v)));
             }
+            None => self.tessellated_shape(ui.ctx(), rect, size, current_color),
         };
 
+        if self.pixel_snapping {
+            tessellation::snap_to_pixels(&mut shape, pixels_per_point);
+        }
+
+        let style_color = ui.style().interact(&response).fg_stroke.color;
+        self.apply_color_override(Some(ui.ctx()), &mut shape, rect, style_color);
+
         match &self.background {
             Background::None => {}
             Background::FromStyle => {
@@ -404,13 +747,229 @@ impl Svg {
 
         response
     }
-    /// original viewbox of the svg shape
-    pub fn svg_rect(&self) -> Rect {
+    /// recolor `shape` drawn in `rect` with the color override. `style_color` is the
+    /// widget's foreground color. without `ctx` texture uvs are mapped uncached
+    fn apply_color_override(
+        &self,
+        ctx: Option<&Context>,
+        shape: &mut Mesh,
+        rect: Rect,
+        style_color: Color32,
+    ) {
+        match &self.color_override {
+            ColorOverride::None => {}
+            ColorOverride::FromStyle => shape
+                .vertices
+                .iter_mut()
+                .for_each(|v| v.color = style_color),
+            ColorOverride::Color(c) => shape.vertices.iter_mut().for_each(|v| v.color = *c),
+            ColorOverride::Texture {
+                texture,
+                uv_rect,
+                transform,
+                wrap_mode,
+            } => {
+                shape.texture_id = *texture;
+                if let TextureTint::Color(tint) = self.texture_tint {
+                    shape.vertices.iter_mut().for_each(|v| v.color = tint);
+                }
+                if self.texture_interact {
+                    let style = Rgba::from(style_color);
+                    shape
+                        .vertices
+                        .iter_mut()
+                        .for_each(|v| v.color = (Rgba::from(v.color) * style).into());
+                }
+                match ctx {
+                    #[cfg(feature = "cached")]
+                    Some(ctx) => cache::map_uvs(ctx, shape, rect, *transform, *uv_rect, *wrap_mode),
+                    _ => texture::map_uvs(
+                        shape,
+                        |pos| transform.transform_pos(((pos - rect.min) / rect.size()).to_pos2()),
+                        *uv_rect,
+                        *wrap_mode,
+                    ),
+                }
+            }
+            ColorOverride::Gradient(_) if self.mesh_gradient().is_some() => {}
+            ColorOverride::Gradient(g) if !cfg!(feature = "gradient") => {
+                diagnostics::report(|| Diagnostic::GradientOverrideFallback);
+                let color = g.average_color(self.gradient_interpolation);
+                shape.vertices.iter_mut().for_each(|v| v.color = color);
+            }
+            ColorOverride::Gradient(g) => {
+                let display = emath::RectTransform::from_to(
+                    Rect::from_min_size(Pos2::ZERO, rect.size()),
+                    self.svg_rect(),
+                );
+                g.place(self.svg_rect(), display).color_mesh(
+                    shape,
+                    rect,
+                    self.svg_rect(),
+                    self.gradient_interpolation,
+                );
+            }
+        };
+    }
+    /// the tessellated icon in `rect`
+    fn tessellated_shape(
+        &self,
+        ctx: &Context,
+        rect: Rect,
+        size: Vec2,
+        current_color: Color32,
+    ) -> Mesh {
+        let pixels_per_point = ctx.pixels_per_point();
+        let scale =
+            tessellation::tessellation_scale(self, size / self.svg_rect().size(), pixels_per_point);
+
+        // nothing keeps the texture between frames, `cached` does
         #[cfg(not(feature = "cached"))]
-        let tree = &self.tree;
+        let (mesh, texture) = {
+            let mesh = tessellation::tessellate(self, scale, pixels_per_point);
+            let texture = mesh
+                .texture
+                .clone()
+                .map(|image| ctx.load_texture("svgicon_gradient", image, TextureOptions::LINEAR));
+            (mesh, texture)
+        };
+
         #[cfg(feature = "cached")]
-        let tree = &self.tree.1;
+        let (mesh, texture) = cache::tessellate(ctx, self, scale, pixels_per_point);
+
+        let mut shape = mesh.to_mesh_with_current_color(rect, current_color);
+        // baked gradients are replaced by color overrides
+        match texture.filter(|_| matches!(self.color_override, ColorOverride::None)) {
+            Some(texture) => shape.texture_id = texture.id(),
+            None if mesh.texture.is_some() => shape
+                .vertices
+                .iter_mut()
+                .for_each(|v| v.uv = epaint::WHITE_UV),
+            None => {}
+        }
 
-        to_egui_rect(tree.view_box.rect)
+        shape
+    }
+    /// the gradient override if it is applied while tessellating
+    fn mesh_gradient(&self) -> Option<&gradient::GradientOverride> {
+        match (&self.color_override, &self.source) {
+            (ColorOverride::Gradient(g), SvgSource::Tree(_))
+                if cfg!(feature = "gradient") && g.is_tessellated(self.gradient_per_element) =>
+            {
+                Some(g)
+            }
+            _ => None,
+        }
     }
+    /// the parsed svg, `None` for pre-tessellated icons
+    fn document(&self) -> Option<&SvgDocument> {
+        match &self.source {
+            #[cfg(not(feature = "cached"))]
+            SvgSource::Tree(tree) => Some(tree),
+            #[cfg(feature = "cached")]
+            SvgSource::Tree((_, tree)) => Some(tree),
+            SvgSource::Mesh(_) => None,
+        }
+    }
+    /// original viewbox of the svg shape
+    pub fn svg_rect(&self) -> Rect {
+        if let Some(element) = &self.element {
+            return element.rect;
+        }
+        match &self.source {
+            #[cfg(not(feature = "cached"))]
+            SvgSource::Tree(tree) => to_egui_rect(tree.view_box.rect),
+            #[cfg(feature = "cached")]
+            SvgSource::Tree((_, tree)) => to_egui_rect(tree.view_box.rect),
+            #[cfg(not(feature = "cached"))]
+            SvgSource::Mesh(mesh) => mesh.view_box,
+            #[cfg(feature = "cached")]
+            SvgSource::Mesh((_, mesh)) => mesh.view_box,
+        }
+    }
+}
+
+fn load_tree(data: &[u8]) -> Result<SvgDocument, usvg::Error> {
+    SvgDocument::parse(data)
+}
+
+/// unzip `.svgz` data
+fn decompress(data: &[u8]) -> Result<std::borrow::Cow<'_, [u8]>, usvg::Error> {
+    Ok(match data.starts_with(&[0x1f, 0x8b]) {
+        true => std::borrow::Cow::Owned(usvg::decompress_svgz(data)?),
+        false => std::borrow::Cow::Borrowed(data),
+    })
+}
+
+/// parse with the same xml options as usvg
+fn parse_xml(text: &str) -> Result<usvg::roxmltree::Document<'_>, usvg::Error> {
+    usvg::roxmltree::Document::parse_with_options(
+        text,
+        usvg::roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .map_err(usvg::Error::ParsingFailed)
+}
+
+/// content based key for `data`. stable between runs, so it can key the disk cache
+#[cfg(feature = "cached")]
+fn content_key<T>(data: &[u8]) -> u64 {
+    cache::stable_hash((std::any::type_name::<T>(), data))
+}
+
+/// cache key for `data`. with `static_cached` each ptr is only hashed once
+#[cfg(feature = "cached")]
+fn data_key<T: 'static>(
+    #[cfg(not(feature = "static_cached"))] data: &[u8],
+    #[cfg(feature = "static_cached")] data: &'static [u8],
+) -> u64 {
+    #[cfg(not(feature = "static_cached"))]
+    return content_key::<T>(data);
+
+    #[cfg(feature = "static_cached")]
+    {
+        use egui::epaint::ahash::*;
+        use std::any::TypeId;
+        use std::cell::RefCell;
+
+        thread_local! {
+            static KEYS: RefCell<HashMap<(TypeId, *const u8, usize), u64>> = Default::default();
+        }
+        KEYS.with(|keys| {
+            *keys
+                .borrow_mut()
+                .entry((TypeId::of::<T>(), data.as_ptr(), data.len()))
+                .or_insert_with(|| content_key::<T>(data))
+        })
+    }
+}
+
+/// load through the thread local cache. `load` is only called on cache misses
+#[cfg(feature = "cached")]
+fn load_cached<T: 'static, E>(
+    key: u64,
+    load: impl FnOnce() -> Result<T, E>,
+) -> Result<(u64, std::rc::Rc<T>), E> {
+    use egui::epaint::ahash::*;
+    use std::any::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    thread_local! {
+        static CACHE: RefCell<HashMap<u64, Rc<dyn Any>>> = Default::default();
+    }
+    CACHE.with(|cache| {
+        let cached = cache.borrow().get(&key).cloned();
+        let value = match cached {
+            Some(value) => value,
+            None => {
+                let value: Rc<dyn Any> = Rc::new(load()?);
+                cache.borrow_mut().insert(key, value.clone());
+                value
+            }
+        };
+        Ok((key, value.downcast().unwrap()))
+    })
 }
@@ -0,0 +1,360 @@
+use crate::*;
+
+const MAGIC: &[u8; 4] = b"SVGM";
+const VERSION: u16 = 2;
+/// vertices have uvs and the mesh has a texture
+const FLAG_TEXTURE: u16 = 1;
+
+/// where a vertex's color came from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VertexPaint {
+    /// plain svg color
+    Color,
+    /// sampled from a svg gradient
+    Gradient,
+    /// a paint server that is not supported yet
+    Unsupported,
+    /// `currentColor`, resolved when drawing. only the alpha of the color is used
+    CurrentColor,
+}
+impl VertexPaint {
+    fn from_u8(v: u8) -> Option<Self> {
+        Some(match v {
+            0 => VertexPaint::Color,
+            1 => VertexPaint::Gradient,
+            2 => VertexPaint::Unsupported,
+            3 => VertexPaint::CurrentColor,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct IconVertex {
+    /// position inside the viewbox, normalized to `0..=1`
+    pub pos: Pos2,
+    pub color: Color32,
+    pub paint: VertexPaint,
+    /// position in [`IconMesh::texture`]
+    pub uv: Pos2,
+}
+
+/// tessellation output that does not depend on the display size
+#[derive(Clone, Debug)]
+pub struct IconMesh {
+    /// original viewbox of the svg shape
+    pub view_box: Rect,
+    pub vertices: Vec<IconVertex>,
+    pub indices: Vec<u32>,
+    /// gradients baked into a texture, see [`Svg::with_gradient_texture`]
+    pub texture: Option<ColorImage>,
+}
+
+#[derive(Debug)]
+pub enum MeshError {
+    /// not a packed mesh
+    Magic,
+    /// packed by an incompatible version of this crate
+    Version(u16),
+    /// data ends early or indices are out of bounds
+    Corrupted,
+}
+impl std::fmt::Display for MeshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::Magic => write!(f, "not a packed svg mesh"),
+            MeshError::Version(v) => write!(f, "unsupported packed mesh version {v}"),
+            MeshError::Corrupted => write!(f, "corrupted packed mesh"),
+        }
+    }
+}
+impl std::error::Error for MeshError {}
+
+impl IconMesh {
+    /// tessellate the svg at its original size.
+    /// tolerance is in svg units when baking
+    pub fn from_svg(svg: &Svg) -> Self {
+        Self::from_svg_sized(svg, svg.svg_rect().size())
+    }
+    /// tessellate the svg to be drawn `size` physical pixels large.
+    /// tolerance is in physical pixels when scaled
+    pub fn from_svg_sized(svg: &Svg, size: Vec2) -> Self {
+        tessellation::tessellate(svg, size / svg.svg_rect().size(), 1.0)
+    }
+    /// map the mesh into `rect`. `currentColor` is black like in svg
+    pub fn to_mesh(&self, rect: Rect) -> Mesh {
+        self.to_mesh_with_current_color(rect, Color32::BLACK)
+    }
+    /// map the mesh into `rect`, drawing `currentColor` with `current_color`
+    pub fn to_mesh_with_current_color(&self, rect: Rect, current_color: Color32) -> Mesh {
+        Mesh {
+            indices: self.indices.clone(),
+            vertices: self
+                .vertices
+                .iter()
+                .map(|v| epaint::Vertex {
+                    pos: rect.min + v.pos.to_vec2() * rect.size(),
+                    uv: v.uv,
+                    color: match v.paint {
+                        VertexPaint::CurrentColor => {
+                            current_color.gamma_multiply(v.color.a() as f32 / 255.0)
+                        }
+                        _ => v.color,
+                    },
+                })
+                .collect(),
+            texture_id: Default::default(),
+        }
+    }
+    /// serialize into the packed binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let flags = match self.texture {
+            Some(_) => FLAG_TEXTURE,
+            None => 0,
+        };
+        let mut out = Vec::with_capacity(32 + self.vertices.len() * 21 + self.indices.len() * 4);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        for f in [
+            self.view_box.min.x,
+            self.view_box.min.y,
+            self.view_box.max.x,
+            self.view_box.max.y,
+        ] {
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.vertices.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.indices.len() as u32).to_le_bytes());
+        for v in &self.vertices {
+            out.extend_from_slice(&v.pos.x.to_le_bytes());
+            out.extend_from_slice(&v.pos.y.to_le_bytes());
+            out.extend_from_slice(&v.color.to_array());
+            out.push(v.paint as u8);
+            if flags & FLAG_TEXTURE != 0 {
+                out.extend_from_slice(&v.uv.x.to_le_bytes());
+                out.extend_from_slice(&v.uv.y.to_le_bytes());
+            }
+        }
+        for i in &self.indices {
+            out.extend_from_slice(&i.to_le_bytes());
+        }
+        if let Some(texture) = &self.texture {
+            for size in texture.size {
+                out.extend_from_slice(&(size as u32).to_le_bytes());
+            }
+            for pixel in &texture.pixels {
+                out.extend_from_slice(&pixel.to_array());
+            }
+        }
+        out
+    }
+    /// deserialize from the packed binary format
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MeshError> {
+        let mut reader = Reader(data);
+        if reader.take(4)? != MAGIC {
+            return Err(MeshError::Magic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(MeshError::Version(version));
+        }
+        let flags = reader.u16()?;
+        let textured = flags & FLAG_TEXTURE != 0;
+        let view_box = Rect::from_min_max(
+            Pos2::new(reader.f32()?, reader.f32()?),
+            Pos2::new(reader.f32()?, reader.f32()?),
+        );
+        let vertex_count = reader.u32()? as usize;
+        let index_count = reader.u32()? as usize;
+        let size = vertex_count
+            .checked_mul(if textured { 21 } else { 13 })
+            .zip(index_count.checked_mul(4))
+            .and_then(|(vertices, indices)| vertices.checked_add(indices))
+            .ok_or(MeshError::Corrupted)?;
+        // the texture follows the indices
+        if reader.0.len() < size || !textured && reader.0.len() != size {
+            return Err(MeshError::Corrupted);
+        }
+        let vertices = (0..vertex_count)
+            .map(|_| {
+                Ok(IconVertex {
+                    pos: Pos2::new(reader.f32()?, reader.f32()?),
+                    color: {
+                        let [r, g, b, a] = reader.array()?;
+                        Color32::from_rgba_premultiplied(r, g, b, a)
+                    },
+                    paint: VertexPaint::from_u8(reader.u8()?).ok_or(MeshError::Corrupted)?,
+                    uv: match textured {
+                        true => Pos2::new(reader.f32()?, reader.f32()?),
+                        false => Pos2::ZERO,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let indices = (0..index_count)
+            .map(|_| match reader.u32()? {
+                i if (i as usize) < vertex_count => Ok(i),
+                _ => Err(MeshError::Corrupted),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let texture = match textured {
+            true => {
+                let size = [reader.u32()? as usize, reader.u32()? as usize];
+                let bytes = size[0].checked_mul(size[1]).and_then(|p| p.checked_mul(4));
+                if bytes != Some(reader.0.len()) {
+                    return Err(MeshError::Corrupted);
+                }
+                let pixels = (0..size[0] * size[1])
+                    .map(|_| {
+                        let [r, g, b, a] = reader.array()?;
+                        Ok(Color32::from_rgba_premultiplied(r, g, b, a))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Some(ColorImage { size, pixels })
+            }
+            false => None,
+        };
+        Ok(IconMesh {
+            view_box,
+            vertices,
+            indices,
+            texture,
+        })
+    }
+}
+
+struct Reader<'l>(&'l [u8]);
+impl<'l> Reader<'l> {
+    fn take(&mut self, n: usize) -> Result<&'l [u8], MeshError> {
+        if self.0.len() < n {
+            return Err(MeshError::Corrupted);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], MeshError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+    fn u8(&mut self) -> Result<u8, MeshError> {
+        Ok(self.array::<1>()?[0])
+    }
+    fn u16(&mut self) -> Result<u16, MeshError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+    fn u32(&mut self) -> Result<u32, MeshError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    fn f32(&mut self) -> Result<f32, MeshError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+}
+
+/// tessellate every `.svg` in `input` and write them to `output` as `.mesh` files, detailed
+/// enough to be drawn with their longer side `size` physical pixels large.
+/// meant to be called from a `build.rs`. returns the number of packed icons
+pub fn pack_dir(
+    input: impl AsRef<std::path::Path>,
+    output: impl AsRef<std::path::Path>,
+    size: f32,
+) -> std::io::Result<usize> {
+    use std::io::*;
+
+    std::fs::create_dir_all(&output)?;
+    let mut count = 0;
+    for entry in std::fs::read_dir(input)? {
+        let path = entry?.path();
+        if path.extension() != Some("svg".as_ref()) {
+            continue;
+        }
+        let svg = Svg::from_tree(
+            load_tree(&std::fs::read(&path)?).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+        );
+        let svg_size = svg.svg_rect().size();
+        let name = path.with_extension("mesh");
+        std::fs::write(
+            output.as_ref().join(name.file_name().unwrap()),
+            IconMesh::from_svg_sized(&svg, svg_size * size / svg_size.max_elem()).to_bytes(),
+        )?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 4">
+        <circle cx="2" cy="2" r="2" fill="#ff0000"/>
+        <rect x="4" y="0" width="4" height="4" fill="currentColor"/>
+    </svg>"##;
+
+    #[test]
+    fn round_trip() {
+        let mesh = IconMesh::from_svg(&Svg::new(SVG));
+        let bytes = mesh.to_bytes();
+        let loaded = IconMesh::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.view_box, mesh.view_box);
+        assert_eq!(loaded.indices, mesh.indices);
+        assert_eq!(loaded.vertices.len(), mesh.vertices.len());
+        for (a, b) in loaded.vertices.iter().zip(&mesh.vertices) {
+            assert_eq!(
+                (a.pos, a.color, a.paint, a.uv),
+                (b.pos, b.color, b.paint, b.uv)
+            );
+        }
+        assert_eq!(loaded.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn sized_is_finer() {
+        let svg = Svg::new(SVG);
+        let small = IconMesh::from_svg(&svg);
+        let large = IconMesh::from_svg_sized(&svg, Vec2::new(512.0, 256.0));
+        assert!(large.vertices.len() > small.vertices.len());
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut bytes = IconMesh::from_svg(&Svg::new(SVG)).to_bytes();
+        bytes[0] = b'X';
+        assert!(matches!(
+            IconMesh::from_bytes(&bytes),
+            Err(MeshError::Magic)
+        ));
+        assert!(matches!(
+            IconMesh::from_bytes(&[]),
+            Err(MeshError::Corrupted)
+        ));
+    }
+
+    #[test]
+    fn bad_version() {
+        let mut bytes = IconMesh::from_svg(&Svg::new(SVG)).to_bytes();
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            IconMesh::from_bytes(&bytes),
+            Err(MeshError::Version(v)) if v == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn corrupted() {
+        let bytes = IconMesh::from_svg(&Svg::new(SVG)).to_bytes();
+        for len in 0..bytes.len() {
+            assert!(IconMesh::from_bytes(&bytes[..len]).is_err(), "{len} bytes");
+        }
+        // a texture too large to exist
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&FLAG_TEXTURE.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16 + 8]);
+        bytes.extend_from_slice(&[0xff; 8]);
+        assert!(matches!(
+            IconMesh::from_bytes(&bytes),
+            Err(MeshError::Corrupted)
+        ));
+    }
+}
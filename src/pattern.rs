@@ -0,0 +1,139 @@
+use crate::*;
+
+/// upper bound of tiles a pattern is repeated in
+const MAX_TILES: usize = 4096;
+
+/// a convex polygon. every point has its weights of the triangle it was cut from
+pub type Polygon = Vec<(Pos2, [f32; 3])>;
+
+/// where the tiles of a pattern go
+pub struct Tiles {
+    /// tile content to pattern space
+    pub content_transform: usvg::Transform,
+    /// pattern space to svg space
+    pub transform: usvg::Transform,
+    /// the first tile in pattern space
+    pub rect: Rect,
+}
+impl Tiles {
+    /// place `pattern` on an element with `bounding_box` and `transform`
+    pub fn new(
+        pattern: &usvg::Pattern,
+        bounding_box: Option<usvg::Rect>,
+        transform: usvg::Transform,
+    ) -> Option<Self> {
+        let bounding_box = bounding_box.and_then(|b| b.to_non_zero_rect());
+        let rect = match pattern.units {
+            usvg::Units::UserSpaceOnUse => pattern.rect,
+            usvg::Units::ObjectBoundingBox => pattern.rect.bbox_transform(bounding_box?),
+        };
+        // tile content has its origin at the tile's corner
+        let content_transform = match (pattern.view_box, pattern.content_units) {
+            (Some(view_box), _) => {
+                usvg::utils::view_box_to_transform(view_box.rect, view_box.aspect, rect.size())
+            }
+            (None, usvg::Units::ObjectBoundingBox) => {
+                let bounding_box = bounding_box?;
+                usvg::Transform::from_scale(bounding_box.width(), bounding_box.height())
+            }
+            (None, usvg::Units::UserSpaceOnUse) => usvg::Transform::default(),
+        }
+        .post_translate(rect.x(), rect.y());
+        Some(Self {
+            content_transform,
+            transform: transform.pre_concat(pattern.transform),
+            rect: to_egui_rect(rect),
+        })
+    }
+    /// offsets in pattern space of the tiles covering `bounds` in svg space,
+    /// `None` if there are too many of them
+    pub fn offsets(&self, bounds: Rect) -> Option<Vec<Vec2>> {
+        let inverse = self.transform.invert()?;
+        let corners = [
+            bounds.left_top(),
+            bounds.right_top(),
+            bounds.left_bottom(),
+            bounds.right_bottom(),
+        ];
+        let bounds = Rect::from_points(&corners.map(|p| map_point(inverse, p)));
+        let size = self.rect.size();
+        let first = ((bounds.min - self.rect.min) / size).floor();
+        let last = ((bounds.max - self.rect.min) / size).floor();
+        let count = (last - first + Vec2::splat(1.0)).max(Vec2::ZERO);
+        let tiles = count.x * count.y;
+        if tiles.is_nan() || tiles > MAX_TILES as f32 {
+            return None;
+        }
+        let mut offsets = Vec::with_capacity(tiles as usize);
+        for y in first.y as i64..=last.y as i64 {
+            for x in first.x as i64..=last.x as i64 {
+                offsets.push(Vec2::new(x as f32, y as f32) * size);
+            }
+        }
+        Some(offsets)
+    }
+}
+
+/// the part of `triangle` inside `rect`
+pub fn clip_to_rect(triangle: [Pos2; 3], rect: Rect) -> Polygon {
+    let mut polygon = triangle
+        .into_iter()
+        .zip([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+        .collect();
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ];
+    for i in 0..4 {
+        polygon = clip_half_plane(&polygon, corners[i], corners[(i + 1) % 4]);
+    }
+    polygon
+}
+
+/// the part of `polygon` inside `triangle`
+pub fn clip_to_triangle(polygon: &Polygon, [a, b, c]: [Pos2; 3]) -> Polygon {
+    // the edges are walked clockwise on screen
+    let [a, b, c] = match (b - a).x * (c - a).y - (b - a).y * (c - a).x < 0.0 {
+        true => [a, c, b],
+        false => [a, b, c],
+    };
+    let polygon = clip_half_plane(polygon, a, b);
+    let polygon = clip_half_plane(&polygon, b, c);
+    clip_half_plane(&polygon, c, a)
+}
+
+/// the part of `polygon` inside the edge from `a` to `b` of a shape walked clockwise on screen
+fn clip_half_plane(polygon: &Polygon, a: Pos2, b: Pos2) -> Polygon {
+    let side = |p: Pos2| (b - a).x * (p - a).y - (b - a).y * (p - a).x;
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &(p, w)) in polygon.iter().enumerate() {
+        let (q, v) = polygon[(i + 1) % polygon.len()];
+        let (sp, sq) = (side(p), side(q));
+        if sp >= 0.0 {
+            clipped.push((p, w));
+        }
+        if (sp >= 0.0) != (sq >= 0.0) {
+            let t = sp / (sp - sq);
+            let weights = [0, 1, 2].map(|k| w[k] + (v[k] - w[k]) * t);
+            clipped.push((p.lerp(q, t), weights));
+        }
+    }
+    clipped
+}
+
+/// the color and uv of a point in `triangle` with the weights `w`
+pub fn interpolate(triangle: &[IconVertex; 3], w: [f32; 3]) -> (Color32, Pos2) {
+    let channel = |c: usize| {
+        let v = (0..3)
+            .map(|k| triangle[k].color[c] as f32 * w[k])
+            .sum::<f32>();
+        v.round().clamp(0.0, 255.0) as u8
+    };
+    let uv = (0..3).fold(Pos2::ZERO, |uv, k| uv + triangle[k].uv.to_vec2() * w[k]);
+    (
+        Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3)),
+        uv,
+    )
+}
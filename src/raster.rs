@@ -0,0 +1,316 @@
+use crate::*;
+use cache::{stable_hash, GenerationCache};
+use std::rc::Rc;
+
+/// path segments above which `RenderMode::Auto` rasterizes an icon
+const AUTO_SEGMENTS: usize = 10_000;
+/// largest side of a rasterized icon, larger ones are drawn as vectors
+const MAX_SIZE: usize = 4096;
+
+/// everything a rasterized icon depends on
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RasterKey {
+    pub source: u64,
+    /// hash of the element id
+    pub element: Option<u64>,
+    /// hash of the per element colors
+    element_colors: u64,
+    current_color: Color32,
+    /// every pixel white, to be tinted by a color override
+    mask: bool,
+    size: [usize; 2],
+}
+
+/// rasterized icons
+pub type RasterCache = GenerationCache<RasterKey, TextureHandle>;
+/// path segments of every source
+pub type SegmentCache = GenerationCache<u64, usize>;
+
+impl Svg {
+    /// rasterize the icon into a texture `size` pixels large, cached while it is used
+    /// every frame. `None` for icons loaded from a mesh or larger than 4096 pixels.
+    /// without a widget to interact with, `currentColor` falls back to the inactive
+    /// fg_stroke instead of the interaction color [`Svg::show`] uses
+    pub fn to_texture(&self, ctx: &Context, size: [usize; 2]) -> Option<TextureHandle> {
+        let current_color = self
+            .current_color
+            .unwrap_or(ctx.style().visuals.widgets.inactive.fg_stroke.color);
+        self.raster_texture(ctx, size, current_color, false)
+    }
+    /// the rasterized icon if the render mode asks for it
+    pub(crate) fn rasterized(
+        &self,
+        ctx: &Context,
+        size: Vec2,
+        current_color: Color32,
+    ) -> Option<TextureHandle> {
+        // overrides that need the shape of the icon are drawn as vectors
+        let mask = match self.color_override {
+            ColorOverride::None => false,
+            ColorOverride::FromStyle | ColorOverride::Color(_) => true,
+            ColorOverride::Texture { .. } | ColorOverride::Gradient(_) => return None,
+        };
+        let SvgSource::Tree((key, tree)) = &self.source else {
+            return None;
+        };
+        let raster = match self.render_mode {
+            RenderMode::Vector => false,
+            RenderMode::Raster => true,
+            RenderMode::Auto => {
+                let segments = ctx.memory_mut(|mem| {
+                    *mem.caches
+                        .cache::<SegmentCache>()
+                        .get_or_insert_with(*key, || count_segments(&tree.root))
+                });
+                segments > AUTO_SEGMENTS
+            }
+        };
+        let size = [size.x.round() as usize, size.y.round() as usize];
+        raster
+            .then(|| self.raster_texture(ctx, size, current_color, mask))
+            .flatten()
+    }
+    fn raster_texture(
+        &self,
+        ctx: &Context,
+        size: [usize; 2],
+        current_color: Color32,
+        mask: bool,
+    ) -> Option<TextureHandle> {
+        let SvgSource::Tree((source, tree)) = &self.source else {
+            return None;
+        };
+        if size[0].max(size[1]) > MAX_SIZE {
+            return None;
+        }
+        let key = RasterKey {
+            source: *source,
+            element: self.element.as_ref().map(|e| stable_hash(&e.id)),
+            element_colors: stable_hash(&self.element_colors),
+            current_color,
+            mask,
+            size,
+        };
+        let texture = ctx.memory_mut(|mem| mem.caches.cache::<RasterCache>().get(&key).cloned());
+        if texture.is_some() {
+            return texture;
+        }
+
+        let image = rasterize(self, tree, size, current_color, mask)?;
+        let texture = cache::upload(
+            ctx,
+            "svgicon_raster",
+            image,
+            |cache: &mut RasterCache, texture| cache.insert(key, texture),
+        );
+        Some(texture)
+    }
+}
+
+/// render the icon into an image `size` pixels large
+fn rasterize(
+    svg: &Svg,
+    document: &SvgDocument,
+    size: [usize; 2],
+    current_color: Color32,
+    mask: bool,
+) -> Option<ColorImage> {
+    #[cfg(feature = "puffin")]
+    puffin::profile_function!();
+
+    let mut tree = document.tree.clone();
+    let mut color = svg.element_colors.fallback;
+    // an element is drawn in place, with the transforms and colors of its ancestors
+    if let Some(element) = &svg.element {
+        color = svg
+            .element_colors
+            .inherited(&tree.root, &element.id, color)
+            .flatten();
+        let node = tree.node_by_id(&element.id)?.clone();
+        let transform = match &node {
+            usvg::Node::Group(g) => g.abs_transform.pre_concat(g.transform.invert()?),
+            node => node.abs_transform(),
+        };
+        tree.root = usvg::Group {
+            transform,
+            children: vec![node],
+            ..Default::default()
+        };
+    }
+    recolor(&mut tree.root, svg, document, color, current_color);
+
+    // the viewbox is mapped onto the whole image
+    let view_box = svg.svg_rect();
+    tree.view_box = usvg::ViewBox {
+        rect: usvg::NonZeroRect::from_xywh(
+            view_box.min.x,
+            view_box.min.y,
+            view_box.width(),
+            view_box.height(),
+        )?,
+        aspect: usvg::AspectRatio {
+            defer: false,
+            align: usvg::Align::None,
+            slice: false,
+        },
+    };
+    tree.size = usvg::Size::from_wh(size[0] as f32, size[1] as f32)?;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size[0] as u32, size[1] as u32)?;
+    resvg::render(&tree, Default::default(), &mut pixmap.as_mut());
+
+    let mut image = ColorImage::from_rgba_premultiplied(size, pixmap.data());
+    if mask {
+        for pixel in &mut image.pixels {
+            *pixel = Color32::from_white_alpha(pixel.a());
+        }
+    }
+    Some(image)
+}
+
+/// apply element colors and the current color to the paint of every path
+fn recolor(
+    group: &mut usvg::Group,
+    svg: &Svg,
+    document: &SvgDocument,
+    parent_color: Option<Color32>,
+    current_color: Color32,
+) {
+    for node in &mut group.children {
+        // the innermost element with an overridden color wins
+        let color_override = svg.element_colors.get(node.id()).or(parent_color);
+        match node {
+            usvg::Node::Group(g) => recolor(g, svg, document, color_override, current_color),
+            usvg::Node::Path(p) => {
+                let current = [false, true].map(|stroke| document.is_current_color(p, stroke));
+                let paints = p
+                    .fill
+                    .iter_mut()
+                    .map(|f| (&mut f.paint, &mut f.opacity, current[0]))
+                    .chain(
+                        p.stroke
+                            .iter_mut()
+                            .map(|s| (&mut s.paint, &mut s.opacity, current[1])),
+                    );
+                for (paint, opacity, current) in paints {
+                    let color = match (color_override, &mut *paint) {
+                        (Some(c), _) => c,
+                        (None, usvg::Paint::Color(_)) if current => current_color,
+                        (None, usvg::Paint::LinearGradient(g)) => {
+                            if let Some(stops) = resolve_stops(document, &g.stops, current_color) {
+                                let mut gradient = (**g).clone();
+                                gradient.base.stops = stops;
+                                *g = Rc::new(gradient);
+                            }
+                            continue;
+                        }
+                        (None, usvg::Paint::RadialGradient(g)) => {
+                            if let Some(stops) = resolve_stops(document, &g.stops, current_color) {
+                                let mut gradient = (**g).clone();
+                                gradient.base.stops = stops;
+                                *g = Rc::new(gradient);
+                            }
+                            continue;
+                        }
+                        _ => continue,
+                    };
+                    let [r, g, b, a] = color.to_srgba_unmultiplied();
+                    *paint = usvg::Paint::Color(usvg::Color::new_rgb(r, g, b));
+                    *opacity = usvg::Opacity::new_clamped(opacity.get() * a as f32 / 255.0);
+                }
+            }
+            usvg::Node::Image(_) | usvg::Node::Text(_) => {}
+        }
+    }
+}
+
+/// the stops with `currentColor` replaced, `None` if there are none
+fn resolve_stops(
+    document: &SvgDocument,
+    stops: &[usvg::Stop],
+    current_color: Color32,
+) -> Option<Vec<usvg::Stop>> {
+    let mut resolved = stops.to_vec();
+    let mut changed = false;
+    for (i, stop) in resolved.iter_mut().enumerate() {
+        if document.is_current_color_stop(stops, i) {
+            let [r, g, b, a] = current_color.to_srgba_unmultiplied();
+            stop.color = usvg::Color::new_rgb(r, g, b);
+            stop.opacity = usvg::Opacity::new_clamped(stop.opacity.get() * a as f32 / 255.0);
+            changed = true;
+        }
+    }
+    changed.then_some(resolved)
+}
+
+fn count_segments(group: &usvg::Group) -> usize {
+    group
+        .children
+        .iter()
+        .map(|node| match node {
+            usvg::Node::Group(g) => count_segments(g),
+            usvg::Node::Path(p) => p.data.len(),
+            usvg::Node::Image(_) | usvg::Node::Text(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIRCLE: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+        <circle cx="2" cy="2" r="1.5" fill="#ff0000" fill-opacity="0.5"/>
+    </svg>"##;
+
+    #[test]
+    fn auto_counts_segments() {
+        let ctx = Context::default();
+        let size = Vec2::splat(16.0);
+        let auto = |svg: &'static [u8]| {
+            Svg::new(svg)
+                .with_render_mode(RenderMode::Auto)
+                .rasterized(&ctx, size, Color32::BLACK)
+                .is_some()
+        };
+        assert!(!auto(CIRCLE));
+        // `static_cached` needs static data
+        let many: &'static str = Box::leak(
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4"><path d="M0 0{}Z"/></svg>"#,
+                " L4 4 L0 4".repeat(AUTO_SEGMENTS / 2 + 1)
+            )
+            .into_boxed_str(),
+        );
+        assert!(auto(many.as_bytes()));
+        // too large to rasterize
+        let large = Svg::new(many.as_bytes()).with_render_mode(RenderMode::Auto);
+        let size = Vec2::splat(MAX_SIZE as f32 + 1.0);
+        assert!(large.rasterized(&ctx, size, Color32::BLACK).is_none());
+    }
+
+    #[test]
+    fn cache_functions_see_textures() {
+        let ctx = Context::default();
+        let svg = Svg::new(CIRCLE);
+        assert!(svg.to_texture(&ctx, [16, 16]).is_some());
+        let stats = Svg::mesh_cache_stats(&ctx);
+        assert_eq!((stats.textures, stats.bytes), (1, 16 * 16 * 4));
+        svg.evict_meshes(&ctx);
+        assert_eq!(Svg::mesh_cache_stats(&ctx).textures, 0);
+        assert!(svg.to_texture(&ctx, [16, 16]).is_some());
+        Svg::clear_mesh_cache(&ctx);
+        assert_eq!(Svg::mesh_cache_stats(&ctx).textures, 0);
+    }
+
+    #[test]
+    fn masks_keep_alpha() {
+        let svg = Svg::new(CIRCLE);
+        let document = svg.document().unwrap();
+        let image = rasterize(&svg, document, [16, 16], Color32::BLACK, false).unwrap();
+        let mask = rasterize(&svg, document, [16, 16], Color32::BLACK, true).unwrap();
+        assert!(image.pixels.iter().any(|p| p.a() > 0));
+        for (pixel, masked) in image.pixels.iter().zip(&mask.pixels) {
+            assert_eq!(*masked, Color32::from_white_alpha(pixel.a()));
+        }
+    }
+}
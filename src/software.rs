@@ -0,0 +1,235 @@
+use crate::*;
+
+impl Svg {
+    /// tessellate the svg for an image `size` pixels large and draw it on the cpu,
+    /// with `grid`×`grid` samples per pixel. `currentColor` is black unless set and
+    /// also stands in for the style color. texture overrides only apply their tint,
+    /// the texture itself lives on the gpu
+    pub fn to_image(&self, size: [usize; 2], grid: u32) -> ColorImage {
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(size[0] as f32, size[1] as f32));
+        let mesh = IconMesh::from_svg_sized(self, rect.size());
+        let current_color = self.current_color.unwrap_or(Color32::BLACK);
+        let mut shape = mesh.to_mesh_with_current_color(rect, current_color);
+        self.apply_color_override(None, &mut shape, rect, current_color);
+        // baked gradients are replaced by color overrides
+        let texture = mesh
+            .texture
+            .as_ref()
+            .filter(|_| matches!(self.color_override, ColorOverride::None));
+        rasterize_mesh(&shape, texture, size, grid)
+    }
+}
+
+impl IconMesh {
+    /// draw the mesh into an image `size` pixels large on the cpu, with `grid`×`grid`
+    /// samples per pixel. `currentColor` is black like in svg
+    pub fn to_image(&self, size: [usize; 2], grid: u32) -> ColorImage {
+        self.to_image_with_current_color(size, grid, Color32::BLACK)
+    }
+    /// draw the mesh into an image `size` pixels large on the cpu, drawing `currentColor`
+    /// with `current_color`
+    pub fn to_image_with_current_color(
+        &self,
+        size: [usize; 2],
+        grid: u32,
+        current_color: Color32,
+    ) -> ColorImage {
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(size[0] as f32, size[1] as f32));
+        let mesh = self.to_mesh_with_current_color(rect, current_color);
+        rasterize_mesh(&mesh, self.texture.as_ref(), size, grid)
+    }
+}
+
+/// draw `mesh` into a transparent image `size` pixels large on the cpu.
+/// positions are in pixels and vertex colors are multiplied with `texture`,
+/// sampled at the nearest texel. every pixel is sampled on a `grid`×`grid` grid,
+/// 1 disables multisampling
+pub fn rasterize_mesh(
+    mesh: &Mesh,
+    texture: Option<&ColorImage>,
+    size: [usize; 2],
+    grid: u32,
+) -> ColorImage {
+    #[cfg(feature = "puffin")]
+    puffin::profile_function!();
+
+    let grid = grid.max(1) as usize;
+    let offsets = (0..grid * grid)
+        .map(|i| Vec2::new((i % grid) as f32 + 0.5, (i / grid) as f32 + 0.5) / grid as f32)
+        .collect::<Vec<_>>();
+    // premultiplied colors of every sample
+    let mut buffer = vec![[0.0f32; 4]; size[0] * size[1] * offsets.len()];
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[triangle[k] as usize]);
+        let area = edge(a.pos, b.pos, c.pos);
+        if area == 0.0 || !area.is_finite() {
+            continue;
+        }
+        // edges are walked the same way for either winding
+        let (b, c) = match area > 0.0 {
+            true => (b, c),
+            false => (c, b),
+        };
+        let area = area.abs();
+        let bounds = Rect::from_points(&[a.pos, b.pos, c.pos]);
+        let x = (bounds.min.x.floor().max(0.0) as usize)..(bounds.max.x.ceil().max(0.0) as usize);
+        let y = (bounds.min.y.floor().max(0.0) as usize)..(bounds.max.y.ceil().max(0.0) as usize);
+        for py in y.start..y.end.min(size[1]) {
+            for px in x.start..x.end.min(size[0]) {
+                for (s, offset) in offsets.iter().enumerate() {
+                    let p = Pos2::new(px as f32, py as f32) + *offset;
+                    let w = [(b, c), (c, a), (a, b)].map(|(u, v)| edge(u.pos, v.pos, p));
+                    // samples on an edge belong to one of the triangles sharing it
+                    let inside = w
+                        .iter()
+                        .zip([(b, c), (c, a), (a, b)])
+                        .all(|(w, (u, v))| *w > 0.0 || *w == 0.0 && is_top_left(u.pos, v.pos));
+                    if !inside {
+                        continue;
+                    }
+                    let w = w.map(|w| w / area);
+                    let uv = [a, b, c]
+                        .iter()
+                        .zip(w)
+                        .fold(Pos2::ZERO, |uv, (v, w)| uv + v.uv.to_vec2() * w);
+                    let texel = texture.map_or(Color32::WHITE, |t| sample(t, uv));
+                    let src = [0, 1, 2, 3].map(|k| {
+                        let color = [a, b, c]
+                            .iter()
+                            .zip(w)
+                            .map(|(v, w)| v.color[k] as f32 * w)
+                            .sum::<f32>();
+                        color.clamp(0.0, 255.0) / 255.0 * texel[k] as f32 / 255.0
+                    });
+                    let dst = &mut buffer[(py * size[0] + px) * offsets.len() + s];
+                    *dst = [0, 1, 2, 3].map(|k| src[k] + dst[k] * (1.0 - src[3]));
+                }
+            }
+        }
+    }
+
+    let pixels = buffer
+        .chunks_exact(offsets.len())
+        .map(|samples| {
+            let channel = |k: usize| {
+                let sum = samples.iter().map(|s| s[k]).sum::<f32>();
+                (sum / samples.len() as f32 * 255.0)
+                    .round()
+                    .clamp(0.0, 255.0) as u8
+            };
+            Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+        })
+        .collect();
+    ColorImage { size, pixels }
+}
+
+/// twice the signed area of the triangle `a`, `b`, `p`, positive when walked clockwise on screen
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b - a).x * (p - a).y - (b - a).y * (p - a).x
+}
+
+/// of a triangle walked clockwise on screen, top edges run right and left edges run up
+fn is_top_left(a: Pos2, b: Pos2) -> bool {
+    (a.y == b.y && b.x > a.x) || b.y < a.y
+}
+
+fn sample(texture: &ColorImage, uv: Pos2) -> Color32 {
+    let [w, h] = texture.size;
+    if w == 0 || h == 0 {
+        return Color32::TRANSPARENT;
+    }
+    let x = ((uv.x * w as f32) as isize).clamp(0, w as isize - 1) as usize;
+    let y = ((uv.y * h as f32) as isize).clamp(0, h as isize - 1) as usize;
+    texture[(x, y)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_square() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+            <rect x="1" y="1" width="2" height="2" fill="#ff0000"/>
+        </svg>"##;
+        let image = Svg::new(svg).to_image([8, 8], 1);
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (2..6).contains(&y);
+                let expected = if inside {
+                    Color32::RED
+                } else {
+                    Color32::TRANSPARENT
+                };
+                assert_eq!(image[(x, y)], expected, "pixel {x} {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn applies_color_overrides() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+            <rect width="4" height="4" fill="#ff0000"/>
+        </svg>"##;
+        let image = Svg::new(svg).with_color(Color32::BLUE).to_image([4, 4], 1);
+        assert_eq!(image[(1, 1)], Color32::BLUE);
+        let image = Svg::new(svg)
+            .with_color_from_style()
+            .with_current_color(Color32::GREEN)
+            .to_image([4, 4], 1);
+        assert_eq!(image[(1, 1)], Color32::GREEN);
+    }
+
+    #[cfg(feature = "gradient")]
+    #[test]
+    fn gradient_overrides_show_middle_stops() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+            <rect width="4" height="4" fill="#000000"/>
+        </svg>"##;
+        let stops = [
+            (0.0, Color32::RED),
+            (0.5, Color32::GREEN),
+            (1.0, Color32::BLUE),
+        ];
+        let image = Svg::new(svg)
+            .with_gradient(
+                &stops,
+                Pos2::ZERO,
+                Pos2::new(4.0, 0.0),
+                TextureWrapMode::Clamp,
+            )
+            .to_image([8, 8], 1);
+        let middle = image[(4, 4)];
+        assert!(
+            middle.g() > 192 && middle.r() < 64 && middle.b() < 64,
+            "{middle:?}"
+        );
+    }
+
+    #[test]
+    fn multisamples_edges() {
+        let mut mesh = Mesh::default();
+        let rect = Rect::from_min_max(Pos2::ZERO, Pos2::new(2.5, 4.0));
+        mesh.add_colored_rect(rect, Color32::WHITE);
+        let image = rasterize_mesh(&mesh, None, [4, 4], 4);
+        assert_eq!(image[(1, 1)], Color32::WHITE);
+        // half of the samples are covered
+        let half = Color32::from_rgba_premultiplied(128, 128, 128, 128);
+        assert_eq!(image[(2, 1)], half);
+        assert_eq!(image[(3, 1)], Color32::TRANSPARENT);
+    }
+
+    #[test]
+    fn shared_edges_are_covered_once() {
+        let color = Color32::from_rgba_premultiplied(0, 0, 128, 128);
+        let mut mesh = Mesh::default();
+        // the diagonal passes through sample centers
+        let rect = Rect::from_min_max(Pos2::ZERO, Pos2::new(4.0, 4.0));
+        mesh.add_colored_rect(rect, color);
+        for grid in [1, 2] {
+            let image = rasterize_mesh(&mesh, None, [4, 4], grid);
+            assert!(image.pixels.iter().all(|c| *c == color), "grid {grid}");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use egui::epaint::ahash::HashMap;
+
+/// split `triangles` where they cross a cut through the mesh. `sides` tells which side
+/// of the cut each corner of a triangle is on, `None` keeps the triangle whole.
+/// `split(data, a, b, side)` adds the vertex where the cut crosses the edge from the
+/// corner `a` alone on its side to `b`. with `separate_sides` each side gets its own
+/// vertex there, for seams
+pub fn split_triangles<D>(
+    data: &mut D,
+    triangles: Vec<[u32; 3]>,
+    sides: impl Fn(&D, [u32; 3]) -> Option<[bool; 3]>,
+    separate_sides: bool,
+    mut split: impl FnMut(&mut D, u32, u32, bool) -> u32,
+) -> Vec<[u32; 3]> {
+    // shared edges are split once so the mesh stays watertight
+    let mut split_edges = HashMap::default();
+    let mut sliced = Vec::with_capacity(triangles.len());
+    for t in triangles {
+        let Some((below, k)) = sides(data, t).and_then(|s| Some((s, lone_corner(s)?))) else {
+            sliced.push(t);
+            continue;
+        };
+        let below = below[k];
+        let [a, b, c] = [t[k], t[(k + 1) % 3], t[(k + 2) % 3]];
+        let mut edge = |b: u32, side: bool| {
+            let key = (a.min(b), a.max(b), separate_sides.then_some(side));
+            *split_edges
+                .entry(key)
+                .or_insert_with(|| split(data, a, b, side))
+        };
+        let (ab, ac) = (edge(b, below), edge(c, below));
+        let (ba, ca) = (edge(b, !below), edge(c, !below));
+        sliced.extend([[a, ab, ac], [ba, b, c], [ba, c, ca]]);
+    }
+    sliced
+}
+
+/// the corner alone on its side of a cut
+pub fn lone_corner(sides: [bool; 3]) -> Option<usize> {
+    (0..3).find(|&k| sides[k] != sides[(k + 1) % 3] && sides[k] != sides[(k + 2) % 3])
+}
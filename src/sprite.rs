@@ -0,0 +1,99 @@
+use crate::*;
+use egui::epaint::ahash::HashMap;
+use std::rc::Rc;
+
+/// a sprite sheet parsed into one tree, with a `<use>` instance of every symbol
+pub struct SpriteSheet {
+    pub tree: Rc<SvgDocument>,
+    /// symbol id to the id of its instance and its viewbox
+    pub symbols: HashMap<String, (String, Rect)>,
+}
+impl SpriteSheet {
+    /// parse `data`, instancing every `<symbol>` at its own viewbox position
+    /// so each one keeps its own coordinates
+    pub fn parse(data: &[u8]) -> Result<Self, usvg::Error> {
+        #[cfg(feature = "puffin")]
+        puffin::profile_function!();
+
+        let data = decompress(data)?;
+        let text = std::str::from_utf8(&data).map_err(|_| usvg::Error::NotAnUtf8Str)?;
+        let doc = parse_xml(text)?;
+
+        let mut instances = String::new();
+        let mut view_boxes = Vec::new();
+        for (i, symbol) in doc
+            .descendants()
+            .filter(|n| n.has_tag_name("symbol"))
+            .enumerate()
+        {
+            let Some(id) = symbol.attribute("id") else {
+                continue;
+            };
+            let use_id = format!("__sprite_{i}");
+            let view_box = symbol.attribute("viewBox").and_then(parse_view_box);
+            instances += &format!(r##"<use href="#{}" id="{use_id}""##, escape(id));
+            if let Some(r) = view_box {
+                // placing the symbol at its viewbox makes its viewbox transform the identity
+                instances += &format!(
+                    r#" x="{}" y="{}" width="{}" height="{}""#,
+                    r.min.x,
+                    r.min.y,
+                    r.width(),
+                    r.height()
+                );
+            }
+            instances += "/>";
+            view_boxes.push((id.to_owned(), use_id, view_box));
+        }
+
+        let root = doc.root_element().range();
+        let close = root.start + text[root.clone()].rfind("</").unwrap_or(root.len());
+        let text = [&text[..close], &instances, &text[close..]].concat();
+        let tree = load_tree(text.as_bytes())?;
+
+        let symbols = view_boxes
+            .into_iter()
+            .filter_map(|(id, use_id, view_box)| {
+                // symbols without a viewbox are framed by their content
+                let rect = view_box.or_else(|| {
+                    let rect = tree.node_by_id(&use_id)?.abs_bounding_box()?;
+                    Some(to_egui_rect(rect.to_non_zero_rect()?))
+                })?;
+                Some((id, (use_id, rect)))
+            })
+            .collect();
+        Ok(Self {
+            tree: Rc::new(tree),
+            symbols,
+        })
+    }
+}
+
+fn parse_view_box(s: &str) -> Option<Rect> {
+    let mut values = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse::<f32>().ok());
+    let [x, y, w, h] = [(); 4].map(|_| values.next().flatten());
+    let rect = Rect::from_min_size(Pos2::new(x?, y?), Vec2::new(w?, h?));
+    (rect.width() > 0.0 && rect.height() > 0.0).then_some(rect)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_sheets_have_no_symbols() {
+        assert!(Svg::from_sprite(b"<svg", "square").is_none());
+        let sheet = include_bytes!("../examples/sprite.svg");
+        assert!(Svg::from_sprite(sheet, "square").is_some());
+        assert!(Svg::from_sprite(sheet, "missing").is_none());
+    }
+}
@@ -3,114 +3,467 @@ use lyon::lyon_tessellation::geometry_builder::*;
 use lyon::lyon_tessellation::*;
 use lyon::math::Point;
 use lyon::path::PathEvent;
+use std::collections::{BTreeMap, BTreeSet};
 
-pub fn tessellate(svg: &Svg, rect: Rect, scale: Vec2) -> Mesh {
+/// tessellate the svg into normalized viewbox space.
+/// `scale` is only used for the tolerance and lod
+pub fn tessellate(svg: &Svg, scale: Vec2, pixels_per_point: f32) -> IconMesh {
     #[cfg(feature = "puffin")]
     puffin::profile_function!();
 
-    #[cfg(not(feature = "cached"))]
-    let tree = &svg.tree;
-    #[cfg(feature = "cached")]
-    let tree = &svg.tree.1;
+    let tree = match &svg.source {
+        #[cfg(not(feature = "cached"))]
+        SvgSource::Tree(tree) => tree,
+        #[cfg(feature = "cached")]
+        SvgSource::Tree((_, tree)) => tree,
+        #[cfg(not(feature = "cached"))]
+        SvgSource::Mesh(mesh) => return (**mesh).clone(),
+        #[cfg(feature = "cached")]
+        SvgSource::Mesh((_, mesh)) => return (**mesh).clone(),
+    };
+
+    let pixel_scale = scale.max_elem() * pixels_per_point;
+    let tolerance = if svg.scale_tolerance {
+        svg.tolerance / pixel_scale
+    } else {
+        svg.tolerance
+    };
+    let min_feature = svg.lod.map_or(0.0, |lod| lod.min_feature) / pixel_scale;
+
+    // an element is drawn in place, with the transforms and colors of its ancestors
+    let fallback = svg.element_colors.fallback;
+    let (nodes, transform, color) = match &svg.element {
+        None => (&tree.root.children[..], Default::default(), fallback),
+        Some(element) => {
+            let color = svg
+                .element_colors
+                .inherited(&tree.root, &element.id, fallback)
+                .flatten();
+            match tree.node_by_id(&element.id) {
+                Some(usvg::Node::Group(g)) => (&g.children[..], g.abs_transform, color),
+                Some(node) => (std::slice::from_ref(node), node.abs_transform(), color),
+                None => (&[][..], Default::default(), None),
+            }
+        }
+    };
 
-    let mut buffer = VertexBuffers::<_, u32>::new();
+    let mut out = Output {
+        buffer: VertexBuffers::new(),
+        atlas: Default::default(),
+    };
     tessellate_recursive(
         svg,
-        scale,
-        rect,
-        &mut buffer,
+        tolerance,
+        min_feature,
+        &mut out,
         &mut FillTessellator::new(),
         &mut StrokeTessellator::new(),
-        &tree.root,
-        Default::default(),
+        nodes,
+        transform,
+        color,
+    );
+
+    let texture = out
+        .atlas
+        .bake(&mut out.buffer.vertices, svg.gradient_interpolation);
+
+    IconMesh {
+        view_box: svg.svg_rect(),
+        vertices: out.buffer.vertices,
+        indices: out.buffer.indices,
+        texture,
+    }
+}
+/// the scale a mesh gets tessellated at.
+/// coarse and medium lod tiers use a fixed scale each.
+/// cached meshes are only rebuilt when the physical pixel scale crosses a power of two
+pub fn tessellation_scale(svg: &Svg, scale: Vec2, pixels_per_point: f32) -> Vec2 {
+    if let Some(lod) = svg.lod {
+        let svg_size = svg.svg_rect().size().max_elem();
+        let pixels = (scale * svg.svg_rect().size()).max_elem() * pixels_per_point;
+        match lod.tier_for(pixels) {
+            LodTier::Coarse => return Vec2::splat(lod.coarse / pixels_per_point / svg_size),
+            LodTier::Medium => return Vec2::splat(lod.medium / pixels_per_point / svg_size),
+            LodTier::Fine => {}
+        }
+    }
+
+    #[cfg(feature = "cached")]
+    if !svg.scale_tolerance && svg.lod.is_none() {
+        return Vec2::splat(1.0);
+    }
+    #[cfg(feature = "cached")]
+    return Vec2::splat(
+        2f32.powi(((scale.max_elem() * pixels_per_point).log2().ceil() as i32).clamp(-16, 16))
+            / pixels_per_point,
     );
 
-    let mut mesh = Mesh::default();
-    std::mem::swap(&mut buffer.vertices, &mut mesh.vertices);
-    std::mem::swap(&mut buffer.indices, &mut mesh.indices);
-    mesh
+    #[cfg(not(feature = "cached"))]
+    scale
+}
+/// move vertices of axis aligned edges onto the physical pixel grid.
+/// sides of a shape thinner than a pixel are kept a pixel apart
+pub fn snap_to_pixels(mesh: &mut Mesh, pixels_per_point: f32) {
+    #[cfg(feature = "puffin")]
+    puffin::profile_function!();
+
+    let snap = |v: f32| (v * pixels_per_point).round() / pixels_per_point;
+    // shorter edges are most likely part of a curve
+    let min_length = 1.0 / pixels_per_point;
+    let epsilon = 0.01 / pixels_per_point;
+    let key = |v: f32| (v / epsilon).round() as i64;
+
+    // snapped lines per axis, by their original position
+    let mut lines = [BTreeMap::new(), BTreeMap::new()];
+    let mut vertex_lines = vec![[None; 2]; mesh.vertices.len()];
+    for triangle in mesh.indices.chunks_exact(3) {
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let (a, b) = (triangle[a] as usize, triangle[b] as usize);
+            let delta = mesh.vertices[b].pos - mesh.vertices[a].pos;
+            for axis in 0..2 {
+                if delta[axis].abs() < epsilon && delta[1 - axis].abs() >= min_length {
+                    let v = (mesh.vertices[a].pos[axis] + mesh.vertices[b].pos[axis]) / 2.0;
+                    lines[axis].insert(key(v), snap(v));
+                    vertex_lines[a][axis] = Some(key(v));
+                    vertex_lines[b][axis] = Some(key(v));
+                }
+            }
+        }
+    }
+    // lines joined by a triangle are two sides of the same shape
+    let mut sides = [BTreeSet::new(), BTreeSet::new()];
+    for triangle in mesh.indices.chunks_exact(3) {
+        for (axis, sides) in sides.iter_mut().enumerate() {
+            let keys = [0, 1, 2].map(|k| vertex_lines[triangle[k] as usize][axis]);
+            for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+                if let (Some(a), Some(b)) = (keys[a], keys[b]) {
+                    if a != b {
+                        sides.insert((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+    }
+    for (lines, sides) in lines.iter_mut().zip(sides) {
+        // in order, so a moved side pushes the sides after it
+        for (a, b) in sides {
+            let min = lines[&a] + 1.0 / pixels_per_point;
+            let b = lines.get_mut(&b).unwrap();
+            if *b < min {
+                *b = min;
+            }
+        }
+    }
+    for (vertex, keys) in mesh.vertices.iter_mut().zip(vertex_lines) {
+        for (axis, key) in keys.into_iter().enumerate() {
+            if let Some(key) = key {
+                vertex.pos[axis] = lines[axis][&key];
+            }
+        }
+    }
+}
+/// everything the tessellation writes to
+struct Output {
+    buffer: VertexBuffers<IconVertex, u32>,
+    atlas: gradient::GradientAtlas,
 }
+
+#[allow(clippy::too_many_arguments)]
 fn tessellate_recursive(
     svg: &Svg,
-    scale: Vec2,
-    rect: Rect,
-    buffer: &mut VertexBuffers<epaint::Vertex, u32>,
+    tolerance: f32,
+    min_feature: f32,
+    out: &mut Output,
     fill_tesselator: &mut FillTessellator,
     stroke_tesselator: &mut StrokeTessellator,
-    parent: &usvg::Group,
+    nodes: &[usvg::Node],
     parent_transform: usvg::Transform,
+    parent_color: Option<Color32>,
 ) {
-    for node in &parent.children {
+    for node in nodes {
+        // the innermost element with an overridden color wins
+        let color_override = svg.element_colors.get(node.id()).or(parent_color);
         match node {
             usvg::Node::Path(p) => {
-                let new_egui_vertex =
-                    |point: Point, paint: &usvg::Paint, opacity: f32| -> epaint::Vertex {
-                        let transform = parent_transform;
-                        let svg_pos = {
-                            let mut point = usvg::tiny_skia_path::Point::from_xy(point.x, point.y);
-                            transform.map_point(&mut point);
-                            Pos2::new(point.x, point.y)
-                        };
-                        let egui_pos = {
-                            let mut pos = svg_pos;
-                            pos -= svg.svg_rect().min.to_vec2();
-                            pos.x *= scale.x;
-                            pos.y *= scale.y;
-                            pos += rect.min.to_vec2();
-                            pos
-                        };
-                        epaint::Vertex {
-                            pos: egui_pos,
+                if p.bounding_box
+                    .and_then(|b| b.transform(parent_transform))
+                    .is_some_and(|b| b.width().max(b.height()) < min_feature)
+                {
+                    continue;
+                }
+                // a gradient override placed in this path's bounding box
+                let mesh_gradient = svg
+                    .mesh_gradient()
+                    .filter(|_| color_override.is_none())
+                    .map(|g| {
+                        let bounding_box = p
+                            .bounding_box
+                            .and_then(|b| b.transform(parent_transform)?.to_non_zero_rect())
+                            .map_or(svg.svg_rect(), to_egui_rect);
+                        g.place(bounding_box, emath::RectTransform::identity(svg.svg_rect()))
+                    });
+                if color_override.is_none() && mesh_gradient.is_none() {
+                    let fill = p.fill.iter().map(|f| &f.paint);
+                    for paint in fill.chain(p.stroke.iter().map(|s| &s.paint)) {
+                        report_paint(&p.id, paint);
+                    }
+                }
+                // svg gradients are built once per paint. `currentColor` stops follow the
+                // current color
+                let svg_gradient = |paint: &usvg::Paint| {
+                    let stops = match paint {
+                        usvg::Paint::LinearGradient(g) => &g.stops,
+                        usvg::Paint::RadialGradient(g) => &g.stops,
+                        _ => return None,
+                    };
+                    if color_override.is_some() || mesh_gradient.is_some() {
+                        return None;
+                    }
+                    let mut colors = gradient::stop_colors(stops);
+                    if let Some(document) = svg.document() {
+                        let current_color = svg.current_color.unwrap_or(Color32::BLACK);
+                        document.resolve_stops(stops, &mut colors, current_color);
+                    }
+                    Some(match paint {
+                        usvg::Paint::LinearGradient(g) if cfg!(feature = "gradient") => {
+                            SvgGradient::Linear(gradient::Gradient {
+                                colors,
+                                ..gradient::Gradient::new(g, parent_transform)
+                            })
+                        }
+                        // radial gradients are not drawn either way
+                        _ => SvgGradient::Average(gradient::average_color(
+                            &colors,
+                            svg.gradient_interpolation,
+                        )),
+                    })
+                };
+                let fill_gradient = p.fill.as_ref().and_then(|f| svg_gradient(&f.paint));
+                let stroke_gradient = p.stroke.as_ref().and_then(|s| svg_gradient(&s.paint));
+                // gradients baked into a texture get a row each
+                let mut atlas_row = |gradient: &Option<SvgGradient>| match gradient {
+                    Some(SvgGradient::Linear(gradient)) if svg.gradient_texture => {
+                        Some(out.atlas.push(gradient.clone()))
+                    }
+                    _ => None,
+                };
+                let fill_row = atlas_row(&fill_gradient);
+                let stroke_row = atlas_row(&stroke_gradient);
+
+                let to_svg_pos =
+                    |point: Point| map_point(parent_transform, Pos2::new(point.x, point.y));
+                let new_icon_vertex = |point: Point,
+                                       paint: &usvg::Paint,
+                                       gradient: Option<&SvgGradient>,
+                                       stroke: bool,
+                                       opacity: f32,
+                                       row: Option<usize>|
+                 -> IconVertex {
+                    let svg_pos = to_svg_pos(point);
+                    let view_box = svg.svg_rect();
+                    let pos = ((svg_pos - view_box.min) / view_box.size()).to_pos2();
+                    if let Some(g) = &mesh_gradient {
+                        return IconVertex {
+                            pos,
+                            color: g
+                                .color_at_pos(svg_pos, svg.gradient_interpolation)
+                                .gamma_multiply(opacity),
+                            paint: VertexPaint::Gradient,
                             uv: Pos2::ZERO,
-                            color: {
-                                match paint {
-                                    usvg::Paint::Color(c) => to_egui_color(*c, opacity),
-                                    #[cfg(feature = "gradient")]
-                                    usvg::Paint::LinearGradient(g) => {
-                                        gradient::Gradient::new(g, transform).color_at_pos(svg_pos)
-                                    }
-                                    _ => Color32::BLACK,
-                                }
-                            },
+                        };
+                    }
+                    let (color, paint, uv) = match (color_override, paint, gradient, row) {
+                        (Some(c), ..) => {
+                            (c.gamma_multiply(opacity), VertexPaint::Color, Pos2::ZERO)
+                        }
+                        (None, usvg::Paint::Color(_), ..)
+                            if svg
+                                .document()
+                                .is_some_and(|d| d.is_current_color(p, stroke)) =>
+                        {
+                            (
+                                Color32::WHITE.gamma_multiply(opacity),
+                                VertexPaint::CurrentColor,
+                                Pos2::ZERO,
+                            )
+                        }
+                        (None, usvg::Paint::Color(c), ..) => {
+                            (to_egui_color(*c, opacity), VertexPaint::Color, Pos2::ZERO)
+                        }
+                        // mapped into the texture once all vertices are known
+                        (None, _, Some(SvgGradient::Linear(g)), Some(row)) => (
+                            Color32::WHITE.gamma_multiply(opacity),
+                            VertexPaint::Gradient,
+                            Pos2::new(g.fac_at_pos(svg_pos), row as f32),
+                        ),
+                        (None, _, Some(SvgGradient::Linear(g)), None) => (
+                            g.color_at_pos(svg_pos, svg.gradient_interpolation)
+                                .gamma_multiply(opacity),
+                            VertexPaint::Gradient,
+                            Pos2::ZERO,
+                        ),
+                        (None, _, Some(SvgGradient::Average(c)), _) => {
+                            (c.gamma_multiply(opacity), VertexPaint::Gradient, Pos2::ZERO)
                         }
+                        _ => (Color32::BLACK, VertexPaint::Unsupported, Pos2::ZERO),
                     };
-                let tolerance = if svg.scale_tolerance {
-                    svg.tolerance / scale.max_elem()
-                } else {
-                    svg.tolerance
+                    IconVertex {
+                        pos,
+                        color,
+                        paint,
+                        uv,
+                    }
+                };
+                // vertex colored gradients get extra vertices where their colors change
+                let subdivide = |buffer: &mut VertexBuffers<_, _>,
+                                 start,
+                                 gradient: Option<&SvgGradient>,
+                                 opacity: f32,
+                                 row: Option<usize>| {
+                    let gradient = match (&mesh_gradient, gradient, row) {
+                        _ if !cfg!(feature = "gradient") => return,
+                        (Some(g), _, _) => g,
+                        (None, Some(SvgGradient::Linear(g)), None) => g,
+                        _ => return,
+                    };
+                    let view_box = svg.svg_rect();
+                    gradient.subdivide(
+                        buffer,
+                        start,
+                        view_box,
+                        svg.gradient_interpolation,
+                        opacity,
+                    );
                 };
-                if let Some(fill) = &p.fill {
+
+                // patterns are drawn as their tiles cut to the shape
+                let pattern = |paint: &usvg::Paint| match paint {
+                    usvg::Paint::Pattern(pattern)
+                        if color_override.is_none() && mesh_gradient.is_none() =>
+                    {
+                        Some(pattern.clone())
+                    }
+                    _ => None,
+                };
+
+                if let Some((fill, pattern)) =
+                    p.fill.as_ref().and_then(|f| Some((f, pattern(&f.paint)?)))
+                {
+                    let mut shape = VertexBuffers::new();
                     fill_tesselator
                         .tessellate(
                             PathConvIter::new(p),
                             &FillOptions::tolerance(tolerance),
-                            &mut BuffersBuilder::new(buffer, |f: FillVertex| {
-                                new_egui_vertex(f.position(), &fill.paint, fill.opacity.get())
+                            &mut BuffersBuilder::new(&mut shape, |f: FillVertex| {
+                                to_svg_pos(f.position())
                             }),
                         )
                         .unwrap();
+                    tessellate_pattern(
+                        svg,
+                        tolerance,
+                        min_feature,
+                        out,
+                        fill_tesselator,
+                        stroke_tesselator,
+                        &pattern.borrow(),
+                        p,
+                        parent_transform,
+                        fill.opacity.get(),
+                        &shape,
+                    );
+                } else if let Some(fill) = &p.fill {
+                    let start = (out.buffer.vertices.len(), out.buffer.indices.len());
+                    fill_tesselator
+                        .tessellate(
+                            PathConvIter::new(p),
+                            &FillOptions::tolerance(tolerance),
+                            &mut BuffersBuilder::new(&mut out.buffer, |f: FillVertex| {
+                                new_icon_vertex(
+                                    f.position(),
+                                    &fill.paint,
+                                    fill_gradient.as_ref(),
+                                    false,
+                                    fill.opacity.get(),
+                                    fill_row,
+                                )
+                            }),
+                        )
+                        .unwrap();
+                    subdivide(
+                        &mut out.buffer,
+                        start,
+                        fill_gradient.as_ref(),
+                        fill.opacity.get(),
+                        fill_row,
+                    );
                 }
-                if let Some(stroke) = &p.stroke {
+                let stroke = p.stroke.as_ref().filter(|s| {
+                    let (sx, sy) = parent_transform.get_scale();
+                    s.width.get() * sx.max(sy) >= min_feature
+                });
+                if let Some((stroke, pattern)) = stroke.and_then(|s| Some((s, pattern(&s.paint)?)))
+                {
+                    let mut shape = VertexBuffers::new();
                     stroke_tesselator
                         .tessellate(
                             PathConvIter::new(p),
                             &to_lyon_stroke(stroke).with_tolerance(tolerance),
-                            &mut BuffersBuilder::new(buffer, |f: StrokeVertex| {
-                                new_egui_vertex(f.position(), &stroke.paint, stroke.opacity.get())
+                            &mut BuffersBuilder::new(&mut shape, |f: StrokeVertex| {
+                                to_svg_pos(f.position())
                             }),
                         )
                         .unwrap();
+                    tessellate_pattern(
+                        svg,
+                        tolerance,
+                        min_feature,
+                        out,
+                        fill_tesselator,
+                        stroke_tesselator,
+                        &pattern.borrow(),
+                        p,
+                        parent_transform,
+                        stroke.opacity.get(),
+                        &shape,
+                    );
+                } else if let Some(stroke) = stroke {
+                    let start = (out.buffer.vertices.len(), out.buffer.indices.len());
+                    stroke_tesselator
+                        .tessellate(
+                            PathConvIter::new(p),
+                            &to_lyon_stroke(stroke).with_tolerance(tolerance),
+                            &mut BuffersBuilder::new(&mut out.buffer, |f: StrokeVertex| {
+                                new_icon_vertex(
+                                    f.position(),
+                                    &stroke.paint,
+                                    stroke_gradient.as_ref(),
+                                    true,
+                                    stroke.opacity.get(),
+                                    stroke_row,
+                                )
+                            }),
+                        )
+                        .unwrap();
+                    subdivide(
+                        &mut out.buffer,
+                        start,
+                        stroke_gradient.as_ref(),
+                        stroke.opacity.get(),
+                        stroke_row,
+                    );
                 }
             }
             usvg::Node::Group(g) => tessellate_recursive(
                 svg,
-                scale,
-                rect,
-                buffer,
+                tolerance,
+                min_feature,
+                out,
                 fill_tesselator,
                 stroke_tesselator,
-                &g,
+                &g.children,
                 parent_transform.pre_concat(g.transform),
+                color_override,
             ),
             usvg::Node::Image(_) | usvg::Node::Text(_) => {}
         }
@@ -142,7 +495,7 @@ impl<'l> Iterator for PathConvIter<'l> {
                     let last = self.prev;
                     let first = self.first;
                     self.needs_end = false;
-                    self.prev = Point::new(x as f32, y as f32);
+                    self.prev = Point::new(x, y);
                     self.deferred = Some(PathEvent::Begin { at: self.prev });
                     self.first = self.prev;
                     Some(PathEvent::End {
@@ -151,7 +504,7 @@ impl<'l> Iterator for PathConvIter<'l> {
                         close: false,
                     })
                 } else {
-                    self.first = Point::new(x as f32, y as f32);
+                    self.first = Point::new(x, y);
                     self.needs_end = true;
                     Some(PathEvent::Begin { at: self.first })
                 }
@@ -162,7 +515,7 @@ impl<'l> Iterator for PathConvIter<'l> {
             })) => {
                 self.needs_end = true;
                 let from = self.prev;
-                self.prev = Point::new(x as f32, y as f32);
+                self.prev = Point::new(x, y);
                 Some(PathEvent::Line {
                     from,
                     to: self.prev,
@@ -175,11 +528,11 @@ impl<'l> Iterator for PathConvIter<'l> {
             )) => {
                 self.needs_end = true;
                 let from = self.prev;
-                self.prev = Point::new(x as f32, y as f32);
+                self.prev = Point::new(x, y);
                 Some(PathEvent::Cubic {
                     from,
-                    ctrl1: Point::new(x1 as f32, y1 as f32),
-                    ctrl2: Point::new(x2 as f32, y2 as f32),
+                    ctrl1: Point::new(x1, y1),
+                    ctrl2: Point::new(x2, y2),
                     to: self.prev,
                 })
             }
@@ -221,3 +574,283 @@ impl<'l> PathConvIter<'l> {
         }
     }
 }
+
+/// draw the tiles of `pattern` that `shape` covers, cut to its triangles in svg space
+#[allow(clippy::too_many_arguments)]
+fn tessellate_pattern(
+    svg: &Svg,
+    tolerance: f32,
+    min_feature: f32,
+    out: &mut Output,
+    fill_tesselator: &mut FillTessellator,
+    stroke_tesselator: &mut StrokeTessellator,
+    pattern: &usvg::Pattern,
+    path: &usvg::Path,
+    transform: usvg::Transform,
+    opacity: f32,
+    shape: &VertexBuffers<Pos2, u32>,
+) {
+    #[cfg(feature = "puffin")]
+    puffin::profile_function!();
+
+    let triangles = shape
+        .indices
+        .chunks_exact(3)
+        .map(|t| {
+            let t = [t[0], t[1], t[2]].map(|i| shape.vertices[i as usize]);
+            (t, Rect::from_points(&t))
+        })
+        .collect::<Vec<_>>();
+    if triangles.is_empty() {
+        return;
+    }
+    let bounds = Rect::from_points(&shape.vertices);
+    let tiles = pattern::Tiles::new(pattern, path.bounding_box, transform);
+    let Some((tiles, offsets)) = tiles.and_then(|tiles| {
+        let offsets = tiles.offsets(bounds)?;
+        Some((tiles, offsets))
+    }) else {
+        diagnostics::report(|| Diagnostic::UnsupportedPaint {
+            id: path.id.clone(),
+            paint: "pattern",
+        });
+        return;
+    };
+
+    // the content is tessellated once in pattern space and cut to the first tile
+    let (first_vertex, first_index) = (out.buffer.vertices.len(), out.buffer.indices.len());
+    let (sx, sy) = tiles
+        .transform
+        .pre_concat(tiles.content_transform)
+        .get_scale();
+    let scale = sx.max(sy);
+    tessellate_recursive(
+        svg,
+        tolerance / scale,
+        min_feature / scale,
+        out,
+        fill_tesselator,
+        stroke_tesselator,
+        &pattern.root.children,
+        tiles.content_transform,
+        None,
+    );
+    let view_box = svg.svg_rect();
+    let content = out.buffer.indices[first_index..]
+        .chunks_exact(3)
+        .filter_map(|t| {
+            let triangle = [t[0], t[1], t[2]].map(|i| out.buffer.vertices[i as usize]);
+            let positions = triangle.map(|v| view_box.min + v.pos.to_vec2() * view_box.size());
+            let polygon = pattern::clip_to_rect(positions, tiles.rect);
+            (polygon.len() >= 3).then_some((triangle, polygon))
+        })
+        .collect::<Vec<_>>();
+    out.buffer.vertices.truncate(first_vertex);
+    out.buffer.indices.truncate(first_index);
+
+    for offset in offsets {
+        let tile_transform = tiles.transform.pre_translate(offset.x, offset.y);
+        // only the shape triangles near this tile are clipped against
+        let tile = tiles.rect;
+        let corners = [
+            tile.left_top(),
+            tile.right_top(),
+            tile.left_bottom(),
+            tile.right_bottom(),
+        ];
+        let tile_bounds = Rect::from_points(&corners.map(|p| map_point(tile_transform, p)));
+        let tile_triangles = triangles
+            .iter()
+            .filter(|(_, bounds)| bounds.intersects(tile_bounds))
+            .collect::<Vec<_>>();
+        if tile_triangles.is_empty() {
+            continue;
+        }
+        for (triangle, polygon) in &content {
+            let polygon = polygon
+                .iter()
+                .map(|(p, w)| (map_point(tile_transform, *p), *w))
+                .collect::<pattern::Polygon>();
+            let polygon_bounds =
+                Rect::from_points(&polygon.iter().map(|(p, _)| *p).collect::<Vec<_>>());
+            for (clip, clip_bounds) in &tile_triangles {
+                if !clip_bounds.intersects(polygon_bounds) {
+                    continue;
+                }
+                let piece = pattern::clip_to_triangle(&polygon, *clip);
+                if piece.len() < 3 {
+                    continue;
+                }
+                let first = out.buffer.vertices.len() as u32;
+                out.buffer.vertices.extend(piece.iter().map(|(p, w)| {
+                    let (color, uv) = pattern::interpolate(triangle, *w);
+                    IconVertex {
+                        pos: ((*p - view_box.min) / view_box.size()).to_pos2(),
+                        color: color.gamma_multiply(opacity),
+                        uv,
+                        ..triangle[0]
+                    }
+                }));
+                for i in 1..piece.len() as u32 - 1 {
+                    out.buffer.indices.extend([first, first + i, first + i + 1]);
+                }
+            }
+        }
+    }
+}
+/// how the gradient paint of a path is drawn
+enum SvgGradient {
+    Linear(gradient::Gradient),
+    /// in the average color of its stops
+    Average(Color32),
+}
+
+/// report paint that is not drawn as written
+fn report_paint(id: &str, paint: &usvg::Paint) {
+    let fallback = match paint {
+        usvg::Paint::LinearGradient(_) => !cfg!(feature = "gradient"),
+        usvg::Paint::RadialGradient(_) => true,
+        usvg::Paint::Color(_) | usvg::Paint::Pattern(_) => false,
+    };
+    if fallback {
+        diagnostics::report(|| Diagnostic::GradientFallback { id: id.to_owned() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect_ys(min_y: f32, max_y: f32, pixels_per_point: f32) -> Vec<f32> {
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(
+            Rect::from_min_max(Pos2::new(0.3, min_y), Pos2::new(10.6, max_y)),
+            Color32::WHITE,
+        );
+        snap_to_pixels(&mut mesh, pixels_per_point);
+        mesh.vertices.iter().map(|v| v.pos.y).collect()
+    }
+
+    #[test]
+    fn snaps_to_pixels() {
+        assert_eq!(rect_ys(1.2, 5.6, 1.0), [1.0, 1.0, 6.0, 6.0]);
+        assert_eq!(rect_ys(1.2, 5.6, 2.0), [1.0, 1.0, 5.5, 5.5]);
+    }
+
+    #[test]
+    fn element_inherits_ancestor_color() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+            <g id="outer">
+                <rect id="inner" width="2" height="2" fill="#000000"/>
+                <rect id="other" x="2" width="2" height="2" fill="#000000"/>
+            </g>
+        </svg>"##;
+        let colors = |svg: Svg| {
+            let mesh = IconMesh::from_svg(&svg);
+            assert!(!mesh.vertices.is_empty());
+            mesh.vertices.iter().map(|v| v.color).collect::<Vec<_>>()
+        };
+        let inner = || Svg::new(svg).with_element("inner");
+        let red = colors(inner().with_element_color("outer", Color32::RED));
+        assert!(red.iter().all(|c| *c == Color32::RED));
+        // the innermost color wins
+        let blue = inner()
+            .with_element_color("outer", Color32::RED)
+            .with_element_color("inner", Color32::BLUE);
+        assert!(colors(blue).iter().all(|c| *c == Color32::BLUE));
+        // siblings don't color each other
+        let black = colors(inner().with_element_color("other", Color32::RED));
+        assert!(black.iter().all(|c| *c == Color32::BLACK));
+    }
+
+    #[test]
+    fn keeps_thin_shapes() {
+        assert_eq!(rect_ys(10.2, 10.5, 1.0), [10.0, 10.0, 11.0, 11.0]);
+        assert_eq!(rect_ys(10.2, 10.3, 2.0), [10.0, 10.0, 10.5, 10.5]);
+    }
+
+    #[test]
+    fn fills_patterns() {
+        // red stripes covering the top half of every tile
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 40 40">
+            <pattern id="p" width="10" height="10" patternUnits="userSpaceOnUse">
+                <rect width="10" height="5" fill="#ff0000"/>
+            </pattern>
+            <rect x="2" y="3" width="30" height="30" fill="url(#p)"/>
+        </svg>"##;
+        let mesh = IconMesh::from_svg(&Svg::new(svg));
+        assert!(mesh.vertices.iter().all(|v| v.color == Color32::RED));
+        let area = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[t[k] as usize].pos * 40.0);
+                ((b - a).x * (c - a).y - (b - a).y * (c - a).x).abs() / 2.0
+            })
+            .sum::<f32>();
+        // 30 rows of which y 5..10, 15..20 and 25..30 are not covered
+        assert!((area - 30.0 * 15.0).abs() < 0.01, "{area}");
+    }
+
+    #[test]
+    fn radial_gradients_fall_back() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 2 2">
+            <radialGradient id="g">
+                <stop offset="0" stop-color="#ff0000"/>
+                <stop offset="1" stop-color="#0000ff"/>
+            </radialGradient>
+            <rect width="2" height="2" fill="url(#g)"/>
+        </svg>"##;
+        let mesh = IconMesh::from_svg(&Svg::new(svg));
+        assert!(!mesh.vertices.is_empty());
+        for v in &mesh.vertices {
+            assert_eq!(v.paint, VertexPaint::Gradient);
+            assert!(v.color.r() > 64 && v.color.b() > 64, "{:?}", v.color);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "gradient")]
+    fn gradient_overrides_keep_opacity() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 2 2">
+            <rect width="2" height="2" fill-opacity="0.5"/>
+        </svg>"##;
+        let colors = [(0.0, Color32::RED), (1.0, Color32::BLUE)];
+        let svg = Svg::new(svg).with_conic_gradient(
+            &colors,
+            Pos2::new(1.0, 1.0),
+            0.0,
+            TextureWrapMode::Clamp,
+        );
+        let mesh = IconMesh::from_svg(&svg);
+        assert!(!mesh.vertices.is_empty());
+        for v in &mesh.vertices {
+            assert!((126..=129).contains(&v.color.a()), "{:?}", v.color);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "gradient")]
+    fn bakes_mixed_gradients() {
+        let svg: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 2">
+            <linearGradient id="l">
+                <stop offset="0" stop-color="#ff0000"/>
+                <stop offset="1" stop-color="#0000ff"/>
+            </linearGradient>
+            <radialGradient id="r">
+                <stop offset="0" stop-color="#ff0000"/>
+                <stop offset="1" stop-color="#0000ff"/>
+            </radialGradient>
+            <rect width="2" height="2" fill="url(#l)"/>
+            <rect x="2" width="2" height="2" fill="url(#r)"/>
+        </svg>"##;
+        let mesh = IconMesh::from_svg(&Svg::new(svg).with_gradient_texture(true));
+        let texture = mesh.texture.as_ref().unwrap();
+        // the radial gradient's average color samples the white row
+        let white = Vec2::new(0.5 / texture.size[0] as f32, 0.5 / texture.size[1] as f32);
+        let radial = mesh.vertices.iter().filter(|v| v.pos.x > 0.75);
+        assert!(radial.clone().count() > 0);
+        assert!(radial.clone().all(|v| v.uv == white.to_pos2()));
+        assert!(mesh.vertices.iter().any(|v| v.uv.y > white.y));
+    }
+}
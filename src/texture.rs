@@ -0,0 +1,241 @@
+use crate::*;
+use egui::epaint::ahash::HashMap;
+use egui::epaint::Vertex;
+
+/// upper bound of lines a mesh is sliced along per axis
+const MAX_CUTS: i64 = 256;
+
+/// set the uvs of `mesh` to `uv` of each vertex, wrapped with `wrap_mode` into `uv_rect`.
+/// triangles are sliced where the texture wraps, so each one samples a single period
+pub fn map_uvs(
+    mesh: &mut Mesh,
+    uv: impl Fn(Pos2) -> Pos2,
+    uv_rect: Rect,
+    wrap_mode: TextureWrapMode,
+) {
+    #[cfg(feature = "puffin")]
+    puffin::profile_function!();
+
+    if mesh.vertices.is_empty() {
+        return;
+    }
+    let mut uvs = mesh.vertices.iter().map(|v| uv(v.pos)).collect::<Vec<_>>();
+    for axis in 0..2 {
+        let (min, max) = uvs
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), uv| {
+                (min.min(uv[axis]), max.max(uv[axis]))
+            });
+        let cuts = match wrap_mode {
+            TextureWrapMode::Clamp => 0..=1,
+            TextureWrapMode::Repeat | TextureWrapMode::Mirror => {
+                (min.floor() as i64 + 1)..=(max.ceil() as i64 - 1)
+            }
+        };
+        // past the limit the outermost periods share their texture coordinates
+        let cuts = *cuts.start()..=(*cuts.end()).min(cuts.start() + MAX_CUTS - 1);
+        for cut in cuts.map(|c| c as f32).filter(|c| *c > min && *c < max) {
+            slice(mesh, &mut uvs, axis, cut);
+        }
+    }
+
+    // every triangle gets its own copy of the vertices it shares across a period
+    let mut cells = HashMap::default();
+    let indices = std::mem::take(&mut mesh.indices);
+    let mut vertices = Vec::with_capacity(mesh.vertices.len());
+    for triangle in indices.chunks_exact(3) {
+        let center = triangle
+            .iter()
+            .fold(Vec2::ZERO, |sum, i| sum + uvs[*i as usize].to_vec2() / 3.0);
+        let cell = [center.x.floor() as i64, center.y.floor() as i64];
+        for &i in triangle {
+            let index = *cells.entry((i, cell)).or_insert_with(|| {
+                let uv = uvs[i as usize];
+                let wrap = |v: f32, cell: i64| match wrap_mode {
+                    TextureWrapMode::Clamp => v.clamp(0.0, 1.0),
+                    TextureWrapMode::Repeat => v - cell as f32,
+                    TextureWrapMode::Mirror if cell % 2 == 0 => v - cell as f32,
+                    TextureWrapMode::Mirror => 1.0 - (v - cell as f32),
+                };
+                let local = Vec2::new(wrap(uv.x, cell[0]), wrap(uv.y, cell[1]));
+                vertices.push(Vertex {
+                    uv: uv_rect.min + local * uv_rect.size(),
+                    ..mesh.vertices[i as usize]
+                });
+                vertices.len() as u32 - 1
+            });
+            mesh.indices.push(index);
+        }
+    }
+    mesh.vertices = vertices;
+}
+
+/// split the triangles of `mesh` crossing the line where `uvs[axis]` is `cut`
+fn slice(mesh: &mut Mesh, uvs: &mut Vec<Pos2>, axis: usize, cut: f32) {
+    let triangles = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        .collect();
+    let triangles = split::split_triangles(
+        &mut (&mut mesh.vertices, uvs),
+        triangles,
+        |(_, uvs), t| Some(t.map(|i| uvs[i as usize][axis] < cut)),
+        false,
+        |(vertices, uvs), a, b, _| {
+            let (ua, ub) = (uvs[a as usize], uvs[b as usize]);
+            let f = (cut - ua[axis]) / (ub[axis] - ua[axis]);
+            let (va, vb) = (vertices[a as usize], vertices[b as usize]);
+            let mut uv = ua.lerp(ub, f);
+            uv[axis] = cut;
+            vertices.push(Vertex {
+                pos: va.pos.lerp(vb.pos, f),
+                uv: va.uv.lerp(vb.uv, f),
+                color: lerp_color(va.color, vb.color, f),
+            });
+            uvs.push(uv);
+            vertices.len() as u32 - 1
+        },
+    );
+    mesh.indices = triangles.into_iter().flatten().collect();
+}
+
+fn lerp_color(a: Color32, b: Color32, f: f32) -> Color32 {
+    let channel = |c: usize| emath::lerp(a[c] as f32..=b[c] as f32, f).round() as u8;
+    Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the unit square in two triangles
+    fn square() -> Mesh {
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        mesh
+    }
+
+    /// the cell of each triangle and the uvs of its vertices
+    fn cells(mesh: &Mesh, uv: impl Fn(Pos2) -> Pos2) -> Vec<([i64; 2], [Pos2; 3], [Pos2; 3])> {
+        mesh.indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[t[k] as usize]);
+                let center = uv(Pos2::new(
+                    (a.pos.x + b.pos.x + c.pos.x) / 3.0,
+                    (a.pos.y + b.pos.y + c.pos.y) / 3.0,
+                ));
+                let cell = [center.x.floor() as i64, center.y.floor() as i64];
+                (cell, [a, b, c].map(|v| uv(v.pos)), [a, b, c].map(|v| v.uv))
+            })
+            .collect()
+    }
+
+    fn close(a: Pos2, b: Pos2) -> bool {
+        (a - b).length() < 1e-4
+    }
+
+    #[test]
+    fn repeats() {
+        let uv = |p: Pos2| (p.to_vec2() * 2.0).to_pos2();
+        let mut mesh = square();
+        map_uvs(
+            &mut mesh,
+            uv,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            TextureWrapMode::Repeat,
+        );
+        let cells = cells(&mesh, uv);
+        let mut seen = cells.iter().map(|(cell, _, _)| *cell).collect::<Vec<_>>();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen, [[0, 0], [0, 1], [1, 0], [1, 1]]);
+        for (cell, uvs, mapped) in cells {
+            for (uv, mapped) in uvs.into_iter().zip(mapped) {
+                let local = uv - Vec2::new(cell[0] as f32, cell[1] as f32);
+                assert!(close(mapped, local), "{mapped:?} {local:?}");
+                assert!((0.0..=1.0).contains(&mapped.x) && (0.0..=1.0).contains(&mapped.y));
+            }
+        }
+    }
+
+    #[test]
+    fn mirrors_odd_cells() {
+        // cells -1 and 0 on both axes
+        let uv = |p: Pos2| (p.to_vec2() * 2.0 - Vec2::splat(1.0)).to_pos2();
+        let mut mesh = square();
+        map_uvs(
+            &mut mesh,
+            uv,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            TextureWrapMode::Mirror,
+        );
+        let cells = cells(&mesh, uv);
+        let mut seen = cells.iter().map(|(cell, _, _)| *cell).collect::<Vec<_>>();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen, [[-1, -1], [-1, 0], [0, -1], [0, 0]]);
+        for (cell, uvs, mapped) in cells {
+            for (uv, mapped) in uvs.into_iter().zip(mapped) {
+                let wrap = |v: f32, cell: i64| match cell % 2 == 0 {
+                    true => v - cell as f32,
+                    false => 1.0 - (v - cell as f32),
+                };
+                let expected = Pos2::new(wrap(uv.x, cell[0]), wrap(uv.y, cell[1]));
+                assert!(close(mapped, expected), "{cell:?} {mapped:?} {expected:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn clamps_into_uv_rect() {
+        let uv_rect = Rect::from_min_max(Pos2::new(0.25, 0.25), Pos2::new(0.5, 0.5));
+        let mut mesh = square();
+        map_uvs(
+            &mut mesh,
+            |p| (p.to_vec2() * 3.0 - Vec2::splat(1.0)).to_pos2(),
+            uv_rect,
+            TextureWrapMode::Clamp,
+        );
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| uv_rect.expand(1e-6).contains(v.uv)));
+    }
+
+    #[test]
+    fn slices_watertight() {
+        let mut mesh = square();
+        let mut uvs = mesh
+            .vertices
+            .iter()
+            .map(|v| (v.pos.to_vec2() * 3.0).to_pos2())
+            .collect::<Vec<_>>();
+        for axis in 0..2 {
+            for cut in [1.0, 2.0] {
+                slice(&mut mesh, &mut uvs, axis, cut);
+            }
+        }
+        // every edge is shared by two triangles, except those along the outline
+        let mut edges = HashMap::<(u32, u32), usize>::default();
+        for t in mesh.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let outline = edges
+            .iter()
+            .filter(|(_, count)| **count == 1)
+            .map(|((a, b), _)| {
+                (mesh.vertices[*a as usize].pos - mesh.vertices[*b as usize].pos).length()
+            })
+            .sum::<f32>();
+        assert!((outline - 4.0).abs() < 1e-4, "{outline}");
+        assert!(edges.values().all(|count| *count <= 2));
+    }
+}
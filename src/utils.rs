@@ -14,7 +14,7 @@ pub fn to_lyon_stroke(stroke: &usvg::Stroke) -> StrokeOptions {
         usvg::LineJoin::MiterClip => LineJoin::MiterClip,
     };
     StrokeOptions::default()
-        .with_line_width(stroke.width.get() as f32)
+        .with_line_width(stroke.width.get())
         .with_line_cap(linecap)
         .with_line_join(linejoin)
 }
@@ -28,7 +28,23 @@ pub fn to_egui_color(color: usvg::Color, opacity: f32) -> egui::Color32 {
 }
 pub fn to_egui_rect(rect: usvg::NonZeroRect) -> egui::Rect {
     egui::Rect::from_min_max(
-        [rect.left() as f32, rect.top() as f32].into(),
-        [rect.right() as f32, rect.bottom() as f32].into(),
+        [rect.left(), rect.top()].into(),
+        [rect.right(), rect.bottom()].into(),
     )
 }
+pub fn map_point(transform: usvg::Transform, pos: egui::Pos2) -> egui::Pos2 {
+    let mut point = usvg::tiny_skia_path::Point::from_xy(pos.x, pos.y);
+    transform.map_point(&mut point);
+    egui::Pos2::new(point.x, point.y)
+}
+/// round to 15 mantissa bits so nearly equal floats share a key.
+/// `-0.0` and `0.0` are the same, all NaNs are the same
+pub fn quantize(v: f32) -> u32 {
+    if v.is_nan() {
+        u32::MAX
+    } else if v == 0.0 {
+        0
+    } else {
+        v.to_bits().wrapping_add(0x80) & !0xff
+    }
+}